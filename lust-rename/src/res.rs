@@ -2,16 +2,24 @@
  * This module resolves names in the AST and produces an IR similar
  * to the AST but with all names resolved to their unique IDs. Names
  * that shadow names from an outer scope are given a new unique ID.
+ *
+ * Dotted paths are resolved here too, since only the resolver knows
+ * what their first segment refers to: `m.a` is the field `:a` of the
+ * record bound to `m`, while `Map.get` is the member `get` of the
+ * module `Map`. Local names shadow modules.
  */
 use log::trace;
-use num_rational::Rational64;
+use lust_syntax::{parse::ast, read::sexpr::Sexpr};
+use lust_utils::{diagnostic::Diagnostic, intern::InternedString, span::Span, unique_id::UniqueId};
 use std::{
-    cell::{Ref, RefCell},
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fmt::Display,
     rc::Rc,
 };
 
+const BUILTIN_MODULES: &[&str] = &["List", "Map", "Set", "String", "Vector"];
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ResError {
     kind: ResErrorKind,
@@ -41,6 +49,9 @@ impl Display for ResError {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ResErrorKind {
     UnboundName(InternedString),
+    UnboundPath(InternedString),
+    // a path without a name after its module, e.g. a lone `Map`
+    IncompletePath(Vec<InternedString>),
 }
 
 impl Display for ResErrorKind {
@@ -49,6 +60,35 @@ impl Display for ResErrorKind {
             ResErrorKind::UnboundName(name) => {
                 write!(f, "unbound name '{}'", name)
             }
+            ResErrorKind::UnboundPath(name) => {
+                write!(f, "'{}' is neither a variable nor a module", name)
+            }
+            ResErrorKind::IncompletePath(path) => {
+                write!(f, "path '{}' doesn't name a value", path.join("."))
+            }
+        }
+    }
+}
+
+impl From<ResError> for Diagnostic {
+    fn from(err: ResError) -> Self {
+        match err.kind {
+            ResErrorKind::UnboundName(name) => {
+                Diagnostic::error(format!("unbound name `{}`", name))
+                    .with_code("E0200")
+                    .with_primary(err.span, "not found in this scope")
+            }
+            ResErrorKind::UnboundPath(name) => {
+                Diagnostic::error(format!("`{}` is neither a variable nor a module", name))
+                    .with_code("E0201")
+                    .with_primary(err.span, "not found in this scope")
+                    .with_help("paths start with a record variable or a module name")
+            }
+            ResErrorKind::IncompletePath(path) => {
+                Diagnostic::error(format!("path `{}` doesn't name a value", path.join(".")))
+                    .with_code("E0202")
+                    .with_primary(err.span, "expected a name after the module")
+            }
         }
     }
 }
@@ -86,62 +126,26 @@ impl Env {
         }
     }
 
-    fn find_in_scope(&self, name: &InternedString) -> Option<UniqueId> {
-        self.data.get(name).copied()
-    }
-
-    fn find_in_parent(&self, name: &InternedString) -> Option<UniqueId> {
-        if let Some(parent) = &self.parent {
-            parent.borrow().find(name)
-        } else {
-            None
-        }
-    }
-
     pub fn define(&mut self, name: InternedString) -> UniqueId {
         let id = UniqueId::gen();
         self.data.insert(name, id);
         id
     }
-
-    // pub fn insert(&mut self, name: InternedString, id: UniqueId) {
-    //     self.data.insert(name, id);
-    // }
-
-    fn define_if_absent_in_scope(&mut self, name: InternedString) -> Option<UniqueId> {
-        if let Some(id) = self.find_in_scope(&name) {
-            Some(id)
-        } else {
-            let id = UniqueId::gen();
-            self.data.insert(name, id);
-            Some(id)
-        }
-    }
-
-    fn define_if_absent(&mut self, name: InternedString) -> UniqueId {
-        if let Some(id) = self.find(&name) {
-            id
-        } else {
-            let id = UniqueId::gen();
-            self.data.insert(name, id);
-            id
-        }
-    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Root {
-    decls: Vec<Decl>,
+    items: Vec<Item>,
     span: Span,
 }
 
 impl Root {
-    pub fn new(decls: Vec<Decl>, span: Span) -> Self {
-        Self { decls, span }
+    pub fn new(items: Vec<Item>, span: Span) -> Self {
+        Self { items, span }
     }
 
-    pub fn decls(&self) -> &[Decl] {
-        &self.decls
+    pub fn items(&self) -> &[Item] {
+        &self.items
     }
 
     pub fn span(&self) -> &Span {
@@ -150,29 +154,34 @@ impl Root {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Item {
-    kind: ItemKind,
-    span: Span,
+pub enum Item {
+    Def(Def),
+    Expr(Expr),
 }
 
-impl Item {
-    pub fn new(kind: ItemKind, span: Span) -> Self {
-        Self { kind, span }
+#[derive(Debug, Clone, PartialEq)]
+pub struct Def {
+    pat: Pattern,
+    expr: Expr,
+    doc: Option<InternedString>,
+}
+
+impl Def {
+    pub fn new(pat: Pattern, expr: Expr, doc: Option<InternedString>) -> Self {
+        Self { pat, expr, doc }
     }
 
-    pub fn kind(&self) -> &ItemKind {
-        &self.kind
+    pub fn pat(&self) -> &Pattern {
+        &self.pat
     }
 
-    pub fn span(&self) -> &Span {
-        &self.span
+    pub fn expr(&self) -> &Expr {
+        &self.expr
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ItemKind {
-    Expr(Expr),
-    Decl(Decl),
+    pub fn doc(&self) -> Option<InternedString> {
+        self.doc
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -200,28 +209,72 @@ impl Expr {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExprKind {
-    Lit(Lit),
+    Lit(ast::Lit),
     Ident(Ident),
-    Apply { fun: Expr, arg: Expr },
-    If { cond: Expr, then: Expr, else_: Expr },
-    Let { name: Ident, expr: Expr, body: Expr },
-    Lambda { param: Ident, expr: Expr },
-    Unit,
+    // `Map.get`
+    Qualified {
+        module: InternedString,
+        name: InternedString,
+    },
+    // `m.a`, looked up with the keyword `:a`
+    Field {
+        expr: Expr,
+        field: InternedString,
+    },
+    Apply {
+        fun: Expr,
+        args: Vec<Expr>,
+    },
+    If {
+        cond: Expr,
+        then: Expr,
+        else_: Expr,
+    },
+    Let {
+        pat: Pattern,
+        expr: Expr,
+        body: Expr,
+    },
+    Letrec {
+        pat: Pattern,
+        expr: Expr,
+        body: Expr,
+    },
+    Match {
+        expr: Expr,
+        arms: Vec<MatchArm>,
+    },
+    Lambda {
+        param: Pattern,
+        expr: Expr,
+    },
+    Do(Vec<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Quote(Sexpr),
+    Quasiquote(Template),
+    List(Vec<Expr>),
+    Vector(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    Set(Vec<Expr>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Ident {
-    name: UniqueId,
+pub struct Template {
+    kind: Box<TemplateKind>,
     span: Span,
 }
 
-impl Ident {
-    pub fn new(name: UniqueId, span: Span) -> Self {
-        Self { name, span }
+impl Template {
+    pub fn new(kind: TemplateKind, span: Span) -> Self {
+        Self {
+            kind: Box::new(kind),
+            span,
+        }
     }
 
-    pub fn name(&self) -> &UniqueId {
-        &self.name
+    pub fn kind(&self) -> &TemplateKind {
+        &self.kind
     }
 
     pub fn span(&self) -> &Span {
@@ -230,24 +283,67 @@ impl Ident {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Lit {
-    Num(Rational64),
-    Bool(bool),
-    String(InternedString),
+pub enum TemplateKind {
+    Datum(Sexpr),
+    Unquote(Expr),
+    UnquoteSplicing(Expr),
+    List(Vec<Template>, Option<Template>),
+    DataList(Vec<Template>),
+    Vector(Vec<Template>),
+    Map(Vec<(Template, Template)>),
+    Set(Vec<Template>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Decl {
-    kind: DeclKind,
+pub struct MatchArm {
+    pat: Pattern,
+    guard: Option<Expr>,
+    expr: Expr,
     span: Span,
 }
 
-impl Decl {
-    pub fn new(kind: DeclKind, span: Span) -> Self {
-        Self { kind, span }
+impl MatchArm {
+    pub fn new(pat: Pattern, guard: Option<Expr>, expr: Expr, span: Span) -> Self {
+        Self {
+            pat,
+            guard,
+            expr,
+            span,
+        }
+    }
+
+    pub fn pat(&self) -> &Pattern {
+        &self.pat
+    }
+
+    pub fn guard(&self) -> Option<&Expr> {
+        self.guard.as_ref()
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
     }
 
-    pub fn kind(&self) -> &DeclKind {
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    kind: Box<PatternKind>,
+    span: Span,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self {
+            kind: Box::new(kind),
+            span,
+        }
+    }
+
+    pub fn kind(&self) -> &PatternKind {
         &self.kind
     }
 
@@ -257,278 +353,749 @@ impl Decl {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum DeclKind {
-    Let { name: Ident, expr: Expr },
+pub enum PatternKind {
+    Wildcard,
+    Lit(ast::Lit),
+    Ident(Ident),
+    // `(x . xs)` and `(x xs...)` bind `xs` as `rest`
+    List {
+        elems: Vec<Pattern>,
+        rest: Option<Pattern>,
+    },
+    Vector {
+        elems: Vec<Pattern>,
+        rest: Option<Pattern>,
+    },
+    Map(Vec<(ast::Lit, Pattern)>),
+    As {
+        pat: Pattern,
+        name: Ident,
+    },
+    // the alternatives bind the same `UniqueId`s
+    Or(Vec<Pattern>),
+    Quote(Sexpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    name: UniqueId,
+    span: Span,
+}
+
+impl Ident {
+    pub fn new(name: UniqueId, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn name(&self) -> &UniqueId {
+        &self.name
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
 }
 
 #[derive(Debug)]
 pub struct Resolver {
-    db: Rc<RefCell<Database>>,
+    modules: HashSet<InternedString>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Resolver {
-    pub fn new(db: Rc<RefCell<Database>>) -> Self {
-        Self { db }
+    pub fn new() -> Self {
+        Self {
+            modules: BUILTIN_MODULES
+                .iter()
+                .map(|&name| InternedString::from(name))
+                .collect(),
+        }
+    }
+
+    /// Makes `name` usable as the first segment of a module path.
+    pub fn define_module(&mut self, name: InternedString) {
+        self.modules.insert(name);
     }
 
     pub fn resolve(
         &mut self,
         env: Rc<RefCell<Env>>,
-        root: &parse::Root,
+        root: &ast::Root,
     ) -> (Option<Root>, Vec<ResError>) {
         let mut errors = vec![];
-        let mut decls = vec![];
-        for decl in root.decls() {
-            match self.resolve_decl(env.clone(), decl) {
-                Ok(d) => {
-                    // trace!("env: {:#?}", env.borrow());
-                    println!("resolver env: {:#?}", env.borrow());
-                    decls.push(d);
-                }
+        let mut items = vec![];
+        for item in &root.items {
+            let res = match item {
+                ast::Item::Def(def) => self.resolve_def(env.clone(), def).map(Item::Def),
+                ast::Item::Expr(expr) => self.resolve_expr(env.clone(), expr).map(Item::Expr),
+            };
+            match res {
+                Ok(item) => items.push(item),
                 Err(err) => {
                     trace!("env: {:#?}", env.borrow());
                     errors.push(err)
                 }
             }
         }
-        if decls.is_empty() {
+        if items.is_empty() {
             (None, errors)
         } else {
             (
                 Some(Root {
-                    decls,
-                    span: root.span().clone(),
+                    items,
+                    span: root.span,
                 }),
                 errors,
             )
         }
     }
 
-    fn resolve_decl(&mut self, env: Rc<RefCell<Env>>, decl: &parse::Decl) -> ResResult<Decl> {
-        trace!("decl env: {:#?}", env.borrow());
-        match decl.kind() {
-            parse::DeclKind::Let { name, expr } => match expr.kind() {
-                parse::ExprKind::Lambda { .. } => {
-                    let name =
-                        Ident::new(env.borrow_mut().define(name.name().clone()), *name.span());
-                    let let_env = Env::new_with_parent(env.clone());
-                    let expr = self.resolve_expr(let_env.clone(), expr)?;
-                    Ok(Decl::new(DeclKind::Let { name, expr }, decl.span().clone()))
-                }
-                _ => {
-                    let let_env = Env::new_with_parent(env.clone());
-                    let expr = self.resolve_expr(let_env.clone(), expr)?;
-                    let name =
-                        Ident::new(env.borrow_mut().define(name.name().clone()), *name.span());
-                    Ok(Decl::new(DeclKind::Let { name, expr }, decl.span().clone()))
-                }
-            },
+    fn resolve_def(&mut self, env: Rc<RefCell<Env>>, def: &ast::Def) -> ResResult<Def> {
+        let (pat, expr) = self.resolve_binding(env, &def.pat, &def.expr)?;
+        Ok(Def::new(pat, expr, def.doc))
+    }
+
+    // Lambdas see their own name so they can recurse; other bindings are
+    // only visible after their definition.
+    fn resolve_binding(
+        &mut self,
+        env: Rc<RefCell<Env>>,
+        pat: &ast::Pattern,
+        expr: &ast::Expr,
+    ) -> ResResult<(Pattern, Expr)> {
+        match *expr.kind {
+            ast::ExprKind::Lambda(..) => {
+                let pat = self.resolve_pattern(env.clone(), pat);
+                let expr = self.resolve_expr(env, expr)?;
+                Ok((pat, expr))
+            }
+            _ => {
+                let expr = self.resolve_expr(env.clone(), expr)?;
+                let pat = self.resolve_pattern(env, pat);
+                Ok((pat, expr))
+            }
         }
     }
 
-    fn resolve_expr(&mut self, env: Rc<RefCell<Env>>, expr: &parse::Expr) -> ResResult<Expr> {
-        match expr.kind() {
-            parse::ExprKind::Lit(l) => match l {
-                parse::Lit::Num(n) => {
-                    Ok(Expr::new(ExprKind::Lit(Lit::Num(n.clone())), *expr.span()))
-                }
-                parse::Lit::Bool(b) => {
-                    Ok(Expr::new(ExprKind::Lit(Lit::Bool(*b)), expr.span().clone()))
+    fn resolve_pattern(&mut self, env: Rc<RefCell<Env>>, pat: &ast::Pattern) -> Pattern {
+        self.resolve_pattern_in(env, pat, false)
+    }
+
+    // With `rebind`, names already bound by an earlier or-pattern
+    // alternative reuse its ids instead of defining new ones.
+    fn resolve_pattern_in(
+        &mut self,
+        env: Rc<RefCell<Env>>,
+        pat: &ast::Pattern,
+        rebind: bool,
+    ) -> Pattern {
+        let bind = |name: InternedString| {
+            let existing = if rebind {
+                env.borrow().find(&name)
+            } else {
+                None
+            };
+            Ident::new(
+                existing.unwrap_or_else(|| env.borrow_mut().define(name)),
+                pat.span,
+            )
+        };
+        let kind = match &*pat.kind {
+            ast::PatternKind::Wildcard => PatternKind::Wildcard,
+            ast::PatternKind::Lit(l) => PatternKind::Lit(l.clone()),
+            ast::PatternKind::Ident(name) => PatternKind::Ident(bind(*name)),
+            ast::PatternKind::As(p, name) => {
+                let name = bind(*name);
+                PatternKind::As {
+                    pat: self.resolve_pattern_in(env.clone(), p, rebind),
+                    name,
                 }
-                parse::Lit::String(s) => Ok(Expr::new(
-                    ExprKind::Lit(Lit::String(s.clone())),
-                    *expr.span(),
-                )),
+            }
+            ast::PatternKind::List(pats) => PatternKind::List {
+                elems: pats
+                    .iter()
+                    .map(|p| self.resolve_pattern_in(env.clone(), p, rebind))
+                    .collect(),
+                rest: pats
+                    .dotted_tail()
+                    .map(|p| self.resolve_pattern_in(env.clone(), p, rebind)),
+            },
+            ast::PatternKind::Vector(pats, rest) => PatternKind::Vector {
+                elems: pats
+                    .iter()
+                    .map(|p| self.resolve_pattern_in(env.clone(), p, rebind))
+                    .collect(),
+                rest: rest
+                    .as_ref()
+                    .map(|p| self.resolve_pattern_in(env.clone(), p, rebind)),
             },
-            parse::ExprKind::Ident(ident) => {
-                if let Some(name) = env.borrow().find(ident.name()) {
+            ast::PatternKind::Map(entries) => PatternKind::Map(
+                entries
+                    .iter()
+                    .map(|(key, p)| (key.clone(), self.resolve_pattern_in(env.clone(), p, rebind)))
+                    .collect(),
+            ),
+            ast::PatternKind::Or(alts) => PatternKind::Or(
+                alts.iter()
+                    .enumerate()
+                    .map(|(i, p)| self.resolve_pattern_in(env.clone(), p, rebind || i > 0))
+                    .collect(),
+            ),
+            ast::PatternKind::Quote(datum) => PatternKind::Quote(datum.clone()),
+        };
+        Pattern::new(kind, pat.span)
+    }
+
+    fn resolve_path(
+        &mut self,
+        env: Rc<RefCell<Env>>,
+        path: &[InternedString],
+        span: Span,
+    ) -> ResResult<Expr> {
+        let incomplete = || ResError::new(ResErrorKind::IncompletePath(path.to_vec()), span);
+        let (first, rest) = path.split_first().ok_or_else(incomplete)?;
+        let (head, fields) = if let Some(id) = env.borrow().find(first) {
+            (ExprKind::Ident(Ident::new(id, span)), rest)
+        } else if self.modules.contains(first) {
+            let (name, fields) = rest.split_first().ok_or_else(incomplete)?;
+            (
+                ExprKind::Qualified {
+                    module: *first,
+                    name: *name,
+                },
+                fields,
+            )
+        } else {
+            return Err(ResError::new(ResErrorKind::UnboundPath(*first), span));
+        };
+        Ok(fields.iter().fold(Expr::new(head, span), |expr, field| {
+            Expr::new(
+                ExprKind::Field {
+                    expr,
+                    field: *field,
+                },
+                span,
+            )
+        }))
+    }
+
+    fn resolve_exprs<'a>(
+        &mut self,
+        env: Rc<RefCell<Env>>,
+        exprs: impl IntoIterator<Item = &'a ast::Expr>,
+    ) -> ResResult<Vec<Expr>> {
+        exprs
+            .into_iter()
+            .map(|e| self.resolve_expr(env.clone(), e))
+            .collect()
+    }
+
+    fn resolve_template(
+        &mut self,
+        env: Rc<RefCell<Env>>,
+        template: &ast::Template,
+    ) -> ResResult<Template> {
+        let mut seq = |templates: &[ast::Template]| {
+            templates
+                .iter()
+                .map(|t| self.resolve_template(env.clone(), t))
+                .collect::<ResResult<Vec<_>>>()
+        };
+        let kind = match &*template.kind {
+            ast::TemplateKind::Datum(datum) => TemplateKind::Datum(datum.clone()),
+            ast::TemplateKind::Unquote(expr) => {
+                TemplateKind::Unquote(self.resolve_expr(env, expr)?)
+            }
+            ast::TemplateKind::UnquoteSplicing(expr) => {
+                TemplateKind::UnquoteSplicing(self.resolve_expr(env, expr)?)
+            }
+            ast::TemplateKind::List(elems, tail) => {
+                let elems = seq(elems)?;
+                let tail = tail
+                    .as_ref()
+                    .map(|t| self.resolve_template(env, t))
+                    .transpose()?;
+                TemplateKind::List(elems, tail)
+            }
+            ast::TemplateKind::DataList(elems) => TemplateKind::DataList(seq(elems)?),
+            ast::TemplateKind::Vector(elems) => TemplateKind::Vector(seq(elems)?),
+            ast::TemplateKind::Set(elems) => TemplateKind::Set(seq(elems)?),
+            ast::TemplateKind::Map(entries) => TemplateKind::Map(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            self.resolve_template(env.clone(), k)?,
+                            self.resolve_template(env.clone(), v)?,
+                        ))
+                    })
+                    .collect::<ResResult<_>>()?,
+            ),
+        };
+        Ok(Template::new(kind, template.span))
+    }
+
+    fn resolve_expr(&mut self, env: Rc<RefCell<Env>>, expr: &ast::Expr) -> ResResult<Expr> {
+        match &*expr.kind {
+            ast::ExprKind::Lit(l) => Ok(Expr::new(ExprKind::Lit(l.clone()), expr.span)),
+            ast::ExprKind::Ident(name) => {
+                if let Some(id) = env.borrow().find(name) {
                     Ok(Expr::new(
-                        ExprKind::Ident(Ident::new(name, expr.span().clone())),
-                        expr.span().clone(),
+                        ExprKind::Ident(Ident::new(id, expr.span)),
+                        expr.span,
                     ))
                 } else {
-                    Err(ResError::new(
-                        ResErrorKind::UnboundName(*ident.name()),
-                        *expr.span(),
-                    ))
+                    Err(ResError::new(ResErrorKind::UnboundName(*name), expr.span))
                 }
             }
-            parse::ExprKind::Apply { fun, arg } => Ok(Expr::new(
-                ExprKind::Apply {
-                    fun: self.resolve_expr(env.clone(), fun)?,
-                    arg: self.resolve_expr(env.clone(), arg)?,
-                },
-                *expr.span(),
-            )),
-            parse::ExprKind::Unary { op, expr } => {
-                let id = env.borrow_mut().define_if_absent(InternedString::from(*op));
-                let ident = Ident::new(id, *op.span());
-                self.db
-                    .borrow_mut()
-                    .insert_or_get(id, InternedString::from(*op));
-                Ok(Expr::new(
-                    ExprKind::Apply {
-                        fun: Expr::new(ExprKind::Ident(ident.clone()), *op.span()),
-                        arg: self.resolve_expr(env.clone(), expr)?,
-                    },
-                    *expr.span(),
-                ))
-            }
-            parse::ExprKind::Binary { op, lhs, rhs } => {
-                let id = env.borrow_mut().define_if_absent(InternedString::from(*op));
-                let ident = Ident::new(id, *op.span());
-                self.db
-                    .borrow_mut()
-                    .insert_or_get(id, InternedString::from(*op));
+            ast::ExprKind::Path(path) => self.resolve_path(env, path, expr.span),
+            ast::ExprKind::Apply(fun, args) => {
+                let fun = self.resolve_expr(env.clone(), fun)?;
+                // `(m.a)` reads the field rather than calling it
+                if args.is_empty() && matches!(fun.kind(), ExprKind::Field { .. }) {
+                    return Ok(Expr::new(*fun.kind, expr.span));
+                }
                 Ok(Expr::new(
                     ExprKind::Apply {
-                        fun: Expr::new(
-                            ExprKind::Apply {
-                                fun: Expr::new(ExprKind::Ident(ident.clone()), *op.span()),
-                                arg: self.resolve_expr(env.clone(), lhs)?,
-                            },
-                            *op.span(),
-                        ),
-                        arg: self.resolve_expr(env.clone(), rhs)?,
+                        fun,
+                        args: self.resolve_exprs(env, args)?,
                     },
-                    *expr.span(),
+                    expr.span,
                 ))
             }
-            parse::ExprKind::If { cond, then, else_ } => Ok(Expr::new(
+            ast::ExprKind::If(cond, then, else_) => Ok(Expr::new(
                 ExprKind::If {
                     cond: self.resolve_expr(env.clone(), cond)?,
                     then: self.resolve_expr(env.clone(), then)?,
-                    else_: self.resolve_expr(env.clone(), else_)?,
+                    else_: self.resolve_expr(env, else_)?,
                 },
-                *expr.span(),
+                expr.span,
             )),
-            parse::ExprKind::Let { name, expr, body } => match expr.kind() {
-                parse::ExprKind::Lambda { .. } => {
-                    let name =
-                        Ident::new(env.borrow_mut().define(name.name().clone()), *name.span());
-                    let let_env = Env::new_with_parent(env.clone());
-                    let res_expr = self.resolve_expr(let_env.clone(), &expr)?;
-                    let body = self.resolve_expr(let_env.clone(), &body)?;
-                    Ok(Expr::new(
-                        ExprKind::Let {
-                            name,
-                            expr: res_expr,
-                            body,
-                        },
-                        *expr.span(),
-                    ))
-                }
-                _ => {
-                    let let_env = Env::new_with_parent(env.clone());
-                    let res_expr = self.resolve_expr(let_env.clone(), expr)?;
-                    let name =
-                        Ident::new(env.borrow_mut().define(name.name().clone()), *name.span());
-                    let body = self.resolve_expr(let_env.clone(), body)?;
-                    Ok(Expr::new(
-                        ExprKind::Let {
-                            name,
-                            expr: res_expr,
-                            body,
-                        },
-                        *expr.span(),
-                    ))
-                }
-            },
-            parse::ExprKind::Lambda { param, expr } => {
-                let lam_env = Env::new_with_parent(env.clone());
-                let param =
-                    Ident::new(env.borrow_mut().define(param.name().clone()), *param.span());
+            ast::ExprKind::Let(pat, let_expr, body) => {
+                let let_env = Env::new_with_parent(env);
+                let (pat, let_expr) = self.resolve_binding(let_env.clone(), pat, let_expr)?;
+                let body = self.resolve_expr(let_env, body)?;
+                Ok(Expr::new(
+                    ExprKind::Let {
+                        pat,
+                        expr: let_expr,
+                        body,
+                    },
+                    expr.span,
+                ))
+            }
+            ast::ExprKind::Letrec(pat, rec_expr, body) => {
+                let rec_env = Env::new_with_parent(env);
+                let pat = self.resolve_pattern(rec_env.clone(), pat);
+                let rec_expr = self.resolve_expr(rec_env.clone(), rec_expr)?;
+                let body = self.resolve_expr(rec_env, body)?;
+                Ok(Expr::new(
+                    ExprKind::Letrec {
+                        pat,
+                        expr: rec_expr,
+                        body,
+                    },
+                    expr.span,
+                ))
+            }
+            ast::ExprKind::Match(scrutinee, arms) => {
+                let scrutinee = self.resolve_expr(env.clone(), scrutinee)?;
+                let arms = arms
+                    .iter()
+                    .map(|arm| {
+                        let arm_env = Env::new_with_parent(env.clone());
+                        let pat = self.resolve_pattern(arm_env.clone(), &arm.pat);
+                        let guard = arm
+                            .guard
+                            .as_ref()
+                            .map(|guard| self.resolve_expr(arm_env.clone(), guard))
+                            .transpose()?;
+                        let expr = self.resolve_expr(arm_env, &arm.expr)?;
+                        Ok(MatchArm::new(pat, guard, expr, arm.span))
+                    })
+                    .collect::<ResResult<_>>()?;
+                Ok(Expr::new(
+                    ExprKind::Match {
+                        expr: scrutinee,
+                        arms,
+                    },
+                    expr.span,
+                ))
+            }
+            ast::ExprKind::Lambda(param, body) => {
+                let lam_env = Env::new_with_parent(env);
+                let param = self.resolve_pattern(lam_env.clone(), param);
                 Ok(Expr::new(
                     ExprKind::Lambda {
                         param,
-                        expr: self.resolve_expr(lam_env.clone(), expr)?,
+                        expr: self.resolve_expr(lam_env, body)?,
                     },
-                    *expr.span(),
+                    expr.span,
                 ))
             }
-            parse::ExprKind::Unit => Ok(Expr::new(ExprKind::Unit, *expr.span())),
+            ast::ExprKind::Do(exprs) => Ok(Expr::new(
+                ExprKind::Do(self.resolve_exprs(env, exprs)?),
+                expr.span,
+            )),
+            ast::ExprKind::And(exprs) => Ok(Expr::new(
+                ExprKind::And(self.resolve_exprs(env, exprs)?),
+                expr.span,
+            )),
+            ast::ExprKind::Or(exprs) => Ok(Expr::new(
+                ExprKind::Or(self.resolve_exprs(env, exprs)?),
+                expr.span,
+            )),
+            ast::ExprKind::Quote(datum) => Ok(Expr::new(ExprKind::Quote(datum.clone()), expr.span)),
+            ast::ExprKind::Quasiquote(template) => Ok(Expr::new(
+                ExprKind::Quasiquote(self.resolve_template(env, template)?),
+                expr.span,
+            )),
+            ast::ExprKind::List(exprs) => Ok(Expr::new(
+                ExprKind::List(
+                    exprs
+                        .iter()
+                        .map(|e| self.resolve_expr(env.clone(), e))
+                        .collect::<ResResult<_>>()?,
+                ),
+                expr.span,
+            )),
+            ast::ExprKind::Vector(exprs) => Ok(Expr::new(
+                ExprKind::Vector(
+                    exprs
+                        .iter()
+                        .map(|e| self.resolve_expr(env.clone(), e))
+                        .collect::<ResResult<_>>()?,
+                ),
+                expr.span,
+            )),
+            ast::ExprKind::Map(entries) => Ok(Expr::new(
+                ExprKind::Map(
+                    entries
+                        .iter()
+                        .map(|(k, v)| {
+                            Ok((
+                                self.resolve_expr(env.clone(), k)?,
+                                self.resolve_expr(env.clone(), v)?,
+                            ))
+                        })
+                        .collect::<ResResult<_>>()?,
+                ),
+                expr.span,
+            )),
+            ast::ExprKind::Set(exprs) => Ok(Expr::new(
+                ExprKind::Set(
+                    exprs
+                        .iter()
+                        .map(|e| self.resolve_expr(env.clone(), e))
+                        .collect::<ResResult<_>>()?,
+                ),
+                expr.span,
+            )),
         }
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use super::{Env, Resolver};
-    use crate::{db::Database, parse::parse};
-    use std::{cell::RefCell, rc::Rc};
-
-    fn test_helper(src: &str) -> super::Root {
-        if let (Some(ast), errors) = parse(src) {
-            let db = Rc::new(RefCell::new(Database::new()));
-            let mut resolver = Resolver::new(db.clone());
-            let (res, errors) = resolver.resolve(Env::new(), &ast);
-            if !errors.is_empty() {
-                panic!("resolve error: {:?}", errors);
-            }
-            res.unwrap()
-        } else {
-            panic!("parse error");
+    use super::{
+        Def, Env, Expr, ExprKind, Item, PatternKind, ResErrorKind, ResResult, Resolver, Root,
+    };
+    use lust_syntax::{
+        parse::{ast, parse},
+        read::read,
+    };
+    use lust_utils::{diagnostic::Diagnostic, span::Span};
+
+    fn resolve(src: &str) -> ResResult<Root> {
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "read errors: {:?}", errs);
+        let (ast, errs) = parse(root);
+        assert!(errs.is_empty(), "parse errors: {:?}", errs);
+        let (res, mut errs) = Resolver::new().resolve(Env::new(), &ast.unwrap());
+        match errs.pop() {
+            Some(err) => Err(err),
+            None => Ok(res.unwrap()),
         }
     }
 
+    fn def(root: &Root, i: usize) -> &Def {
+        match &root.items()[i] {
+            Item::Def(def) => def,
+            Item::Expr(_) => panic!("expected a definition"),
+        }
+    }
+
+    fn expr(root: &Root, i: usize) -> &Expr {
+        match &root.items()[i] {
+            Item::Expr(expr) => expr,
+            Item::Def(_) => panic!("expected an expression"),
+        }
+    }
+
+    #[test]
+    fn res_error_diagnostic() {
+        let err = resolve("(def x y)").unwrap_err();
+        assert_eq!(
+            Diagnostic::from(err).to_string(),
+            "error[E0200]: unbound name `y` @ 7..8"
+        );
+    }
+
+    #[test]
+    fn res_field_access() {
+        let root = resolve("(def m {:a {:b 1}}) (def x m.a.b)").unwrap();
+        let ExprKind::Field { expr, field } = def(&root, 1).expr().kind() else {
+            panic!("expected field access");
+        };
+        assert_eq!(&**field, "b");
+        let ExprKind::Field { expr, field } = expr.kind() else {
+            panic!("expected field access");
+        };
+        assert_eq!(&**field, "a");
+        assert!(matches!(expr.kind(), ExprKind::Ident(_)));
+    }
+
+    #[test]
+    fn res_fn_defs() {
+        let root = resolve(
+            r#"(def (fact n) "Factorial." (if (= n 0) 1 (* n (fact (- n 1)))))
+               (def (sum . xs) xs)"#,
+        );
+        let err = root.unwrap_err();
+        // the operators are unbound, but `fact` sees itself and `n`
+        assert!(matches!(err.kind(), ResErrorKind::UnboundName(name) if &**name == "="));
+
+        let root = resolve("(def (sum x . xs) (sum xs)) (sum 1 2)").unwrap();
+        assert_eq!(def(&root, 0).doc(), None);
+        let ExprKind::Lambda { param, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(
+            param.kind(),
+            PatternKind::List { rest: Some(_), .. }
+        ));
+        let root = resolve(r#"(def (f) "doc" 1)"#).unwrap();
+        assert_eq!(def(&root, 0).doc(), Some("doc".into()));
+    }
+
+    #[test]
+    fn res_multi_clause_defs() {
+        let root = resolve("(def (len []) 0) (def (len (x . xs)) (len xs)) (len [1])").unwrap();
+        assert_eq!(root.items().len(), 2);
+        let ExprKind::Lambda { expr, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected lambda");
+        };
+        let ExprKind::Match { arms, .. } = expr.kind() else {
+            panic!("expected match");
+        };
+        assert_eq!(arms.len(), 2);
+    }
+
+    #[test]
+    fn res_loops() {
+        // the loop sees itself, its parameters and the enclosing `n`
+        let root = resolve("(def (count n) (let loop ((i n)) (if i (loop i) n)))").unwrap();
+        let ExprKind::Lambda { expr, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected lambda");
+        };
+        let ExprKind::Apply { fun, .. } = expr.kind() else {
+            panic!("expected application");
+        };
+        assert!(matches!(fun.kind(), ExprKind::Letrec { .. }));
+
+        let root = resolve("(def (count n) (letf (loop i) (loop i) (loop n)))").unwrap();
+        let ExprKind::Lambda { expr, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(expr.kind(), ExprKind::Letrec { .. }));
+        let err = resolve("(let loop ((i loop)) i)").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("loop".into()));
+    }
+
+    #[test]
+    fn res_rich_patterns() {
+        let root = resolve(
+            "(def (f v)
+               (match v
+                 ((or (:some x) #[x _]) x)
+                 ((as {:k k} m) (Map.get m k))
+                 (n when n n)))",
+        )
+        .unwrap();
+        let ExprKind::Lambda { expr, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected lambda");
+        };
+        let ExprKind::Match { arms, .. } = expr.kind() else {
+            panic!("expected match");
+        };
+        let PatternKind::Or(alts) = arms[0].pat().kind() else {
+            panic!("expected or-pattern");
+        };
+        let PatternKind::List { elems, .. } = alts[0].kind() else {
+            panic!("expected list pattern");
+        };
+        let PatternKind::Vector { elems: velems, .. } = alts[1].kind() else {
+            panic!("expected vector pattern");
+        };
+        // both alternatives bind the same `x`
+        let (PatternKind::Ident(a), PatternKind::Ident(b)) = (elems[1].kind(), velems[0].kind())
+        else {
+            panic!("expected identifiers");
+        };
+        assert_eq!(a.name(), b.name());
+        assert!(matches!(velems[1].kind(), PatternKind::Wildcard));
+        assert!(matches!(arms[1].pat().kind(), PatternKind::As { .. }));
+        assert!(arms[2].guard().is_some());
+
+        let err = resolve("(match 1 (_ when y 1))").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("y".into()));
+        // quoted patterns match a symbol rather than binding it
+        let err = resolve("(match 1 ('a a))").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("a".into()));
+    }
+
+    #[test]
+    fn res_module_path() {
+        let root = resolve("(def g Map.get)").unwrap();
+        let ExprKind::Qualified { module, name } = def(&root, 0).expr().kind() else {
+            panic!("expected qualified name");
+        };
+        assert_eq!((&**module, &**name), ("Map", "get"));
+    }
+
+    #[test]
+    fn res_local_shadows_module() {
+        let root = resolve("(def Map {:get 1}) (def g Map.get)").unwrap();
+        assert!(matches!(
+            def(&root, 1).expr().kind(),
+            ExprKind::Field { .. }
+        ));
+    }
+
+    #[test]
+    fn res_user_module() {
+        let (root, _) = read("(def f Http.get)");
+        let (ast, _) = parse(root);
+        let mut resolver = Resolver::new();
+        resolver.define_module("Http".into());
+        let (_, errs) = resolver.resolve(Env::new(), &ast.unwrap());
+        assert!(errs.is_empty(), "{:?}", errs);
+    }
+
+    #[test]
+    fn res_unbound_path() {
+        let err = resolve("(def x q.a)").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundPath("q".into()));
+    }
+
+    #[test]
+    fn res_incomplete_path() {
+        // the reader never produces these, so build them by hand
+        for path in [vec![], vec!["Map".into()]] {
+            let span = Span::new(0, 3);
+            let ast = ast::Root {
+                items: vec![ast::Item::Expr(ast::Expr::new(
+                    ast::ExprKind::Path(path.clone()),
+                    span,
+                ))],
+                span,
+            };
+            let (_, errs) = Resolver::new().resolve(Env::new(), &ast);
+            assert_eq!(
+                errs.iter().map(|err| err.kind()).collect::<Vec<_>>(),
+                [&ResErrorKind::IncompletePath(path)]
+            );
+        }
+    }
+
+    #[test]
+    fn res_unbound_name() {
+        let err = resolve("(def x x)").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("x".into()));
+    }
+
     #[test]
     fn res_let() {
-        insta::assert_debug_snapshot!(test_helper("let x = 1"));
+        let root = resolve("(def x 1)").unwrap();
+        assert!(matches!(def(&root, 0).pat().kind(), PatternKind::Ident(_)));
+        assert!(matches!(def(&root, 0).expr().kind(), ExprKind::Lit(_)));
     }
 
     #[test]
     fn res_let_error() {
-        let src = "let x = x";
-        if let (Some(ast), errors) = parse(src) {
-            let db = Rc::new(RefCell::new(Database::new()));
-            let mut r = Resolver::new(db.clone());
-            let (_, errors) = r.resolve(Env::new(), &ast);
-            assert!(!errors.is_empty());
-        } else {
-            panic!("parse error");
-        }
+        let err = resolve("(def a (let ((x x)) x))").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("x".into()));
     }
 
     #[test]
     fn res_nested() {
-        insta::assert_debug_snapshot!(test_helper("let a = let x = 1 in let y = 2 in x + y"));
-    }
-
-    // #[test]
-    // fn res_fn_def() {
-    //     let (ast, errors) = crate::syntax::parse::parse("add x y = x + y");
-    //     if !errors.is_empty() {
-    //         panic!("parse error: {:?}", errors);
-    //     }
-    //     let (res, errors) = super::resolve(&ast.unwrap());
-    //     if !errors.is_empty() {
-    //         panic!("resolve error: {:?}", errors);
-    //     }
-    //     insta::assert_debug_snapshot!(res);
-    // }
-
-    // #[test]
-    // fn res_rec_fn_def() {
-    //     let (ast, errors) = crate::syntax::parse::parse("f x = f x");
-    //     if !errors.is_empty() {
-    //         panic!("parse error: {:?}", errors);
-    //     }
-    //     let (res, errors) = super::resolve(&ast.unwrap());
-    //     if !errors.is_empty() {
-    //         panic!("resolve error: {:?}", errors);
-    //     }
-    //     insta::assert_debug_snapshot!(res);
-    // }
-
-    // #[test]
-    // fn res_rec_let() {
-    //     let (ast, errors) = crate::syntax::parse::parse("let f x = f x in f 1");
-    //     if !errors.is_empty() {
-    //         panic!("parse error: {:?}", errors);
-    //     }
-    //     let (res, errors) = super::resolve(&ast.unwrap());
-    //     if !errors.is_empty() {
-    //         panic!("resolve error: {:?}", errors);
-    //     }
-    //     insta::assert_debug_snapshot!(res);
-    // }
+        let root = resolve("(def a (let ((x 1)) (let ((y 2)) #[x y])))").unwrap();
+        let ExprKind::Let { pat: x, body, .. } = def(&root, 0).expr().kind() else {
+            panic!("expected let");
+        };
+        let ExprKind::Let { pat: y, body, .. } = body.kind() else {
+            panic!("expected let");
+        };
+        let (PatternKind::Ident(x), PatternKind::Ident(y)) = (x.kind(), y.kind()) else {
+            panic!("expected ident patterns");
+        };
+        let ExprKind::Vector(uses) = body.kind() else {
+            panic!("expected vector");
+        };
+        assert!(matches!(uses[0].kind(), ExprKind::Ident(id) if id.name() == x.name()));
+        assert!(matches!(uses[1].kind(), ExprKind::Ident(id) if id.name() == y.name()));
+    }
+
+    #[test]
+    fn res_rest_patterns() {
+        let root = resolve("(def [x xs...] [1 2 3]) (def [y ys...] xs) (def z ys)").unwrap();
+        for i in 0..2 {
+            let def = def(&root, i);
+            let PatternKind::List { elems, rest } = def.pat().kind() else {
+                panic!("expected list pattern");
+            };
+            assert_eq!(elems.len(), 1);
+            assert!(matches!(
+                rest.as_ref().unwrap().kind(),
+                PatternKind::Ident(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn res_method_call_sugar() {
+        let root = resolve("(def m {:a 1}) (m.a) (Map.get m :a)").unwrap();
+        assert!(matches!(expr(&root, 1).kind(), ExprKind::Field { .. }));
+        let ExprKind::Apply { fun, args } = expr(&root, 2).kind() else {
+            panic!("expected application");
+        };
+        assert!(matches!(fun.kind(), ExprKind::Qualified { .. }));
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn res_special_forms() {
+        let root = resolve(
+            "(def f (fn (x . xs) (let ((y x) (z y)) (if (and y z) `(,y ,@xs) 'z))))
+             (def g (fn (n) (match n (0 1) (k (g (- k 1))))))",
+        );
+        let ResErrorKind::UnboundName(name) = root.unwrap_err().kind().clone() else {
+            panic!("expected an unbound name");
+        };
+        // only `-` is unbound; `g` sees itself and `k` is bound by its arm
+        assert_eq!(&*name, "-");
+    }
+
+    #[test]
+    fn res_let_scopes() {
+        let err = resolve("(def f (fn (x) (let ((y x)) y))) (def z y)").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("y".into()));
+        let err = resolve("(def f (match 1 (x x) (y x)))").unwrap_err();
+        assert_eq!(err.kind(), &ResErrorKind::UnboundName("x".into()));
+    }
 }
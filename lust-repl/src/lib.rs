@@ -1,40 +1,49 @@
-use lust_syntax::read::read;
+use lust_rename::res::{Env, Resolver};
+use lust_syntax::{
+    parse::parse,
+    read::{input_status, read, InputStatus},
+};
+use lust_utils::{
+    diagnostic::Diagnostic,
+    source::{FileId, SourceMap},
+};
 use std::{
-    cell::RefCell,
-    collections::HashMap,
+    fs,
     io::{self, Write},
-    rc::Rc,
+    path::Path,
 };
 
 pub fn repl() {
     let mut src = String::new();
+    let mut sources = SourceMap::new();
+    // definitions stay in scope for the rest of the session
+    let env = Env::new();
+    let mut resolver = Resolver::new();
     // let mut compiler = Compiler::default();
     // let mut vm = Interpreter::default();
     // let store = Store::new();
     loop {
-        print!("> ");
+        print!("{}", if src.is_empty() { "> " } else { ". " });
         io::stdout().flush().unwrap();
+        let start = src.len();
         io::stdin()
             .read_line(&mut src)
             .expect("Failed to read line");
-        match src.trim() {
-            "exit" => break,
-            _ => (),
+        if src[start..].trim() == "exit" {
+            break;
+        }
+        // keep reading lines until every open form is closed
+        if let InputStatus::Incomplete { .. } = input_status(&src) {
+            continue;
+        }
+        let file = sources.add("<repl>", src.as_str());
+        let (root, errs) = read(&src);
+        println!("sexprs: {:#?}", root);
+        if !errs.is_empty() {
+            report(&sources, file, errs);
+            src.clear();
+            continue;
         }
-        let root = match read(&src) {
-            (Some(root), errs) => {
-                println!("sexprs: {:#?}", root);
-                if !errs.is_empty() {
-                    println!("errs: {:?}", errs);
-                    continue;
-                }
-                root
-            }
-            (None, errs) => {
-                println!("errs: {:?}", errs);
-                continue;
-            }
-        };
         // println!("macros: {:#?}", collect_macros(&root));
         // for m in collect_macros(&root) {
         //     println!("{}", m);
@@ -44,7 +53,15 @@ pub fn repl() {
         if let (Some(ast), errors) = parse(root) {
             println!("ast: {:#?}", ast);
             if !errors.is_empty() {
-                println!("errors: {:?}", errors);
+                report(&sources, file, errors);
+                src.clear();
+                continue;
+            }
+            let (res, errors) = resolver.resolve(env.clone(), &ast);
+            println!("res: {:#?}", res);
+            if !errors.is_empty() {
+                report(&sources, file, errors);
+                src.clear();
                 continue;
             }
         }
@@ -52,3 +69,41 @@ pub fn repl() {
         src.clear();
     }
 }
+
+/// Reads, parses and resolves the file at `path`, reporting every error to
+/// stderr.
+/// Returns whether the file was free of errors.
+pub fn check_file(path: &Path) -> io::Result<bool> {
+    let src = fs::read_to_string(path)?;
+    let mut sources = SourceMap::new();
+    let file = sources.add(path.display().to_string(), src.as_str());
+    let (root, errs) = read(&src);
+    if !errs.is_empty() {
+        report(&sources, file, errs);
+        return Ok(false);
+    }
+    let (ast, errs) = parse(root);
+    if !errs.is_empty() {
+        report(&sources, file, errs);
+        return Ok(false);
+    }
+    let Some(ast) = ast else {
+        return Ok(true);
+    };
+    let (_, errs) = Resolver::new().resolve(Env::new(), &ast);
+    let ok = errs.is_empty();
+    report(&sources, file, errs);
+    Ok(ok)
+}
+
+fn report(
+    sources: &SourceMap,
+    file: FileId,
+    errs: impl IntoIterator<Item = impl Into<Diagnostic>>,
+) {
+    for err in errs {
+        err.into()
+            .eprint(sources, file)
+            .expect("Failed to write diagnostic");
+    }
+}
@@ -1,3 +1,2 @@
-pub mod expand;
 pub mod parse;
 pub mod read;
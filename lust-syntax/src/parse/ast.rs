@@ -1,3 +1,4 @@
+use crate::read::sexpr::Sexpr;
 use lust_utils::{
     intern::InternedString,
     list::List,
@@ -7,12 +8,24 @@ use lust_utils::{
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Root {
-    pub defs: Vec<Def>,
+    pub items: Vec<Item>,
     pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Def(pub Pattern, pub Expr);
+pub enum Item {
+    Def(Def),
+    Expr(Expr),
+}
+
+// `(def (f params...) body...)` is lowered to a `Lambda` bound to `f`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Def {
+    pub pat: Pattern,
+    pub expr: Expr,
+    pub doc: Option<InternedString>,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Expr {
@@ -33,19 +46,75 @@ impl Expr {
 pub enum ExprKind {
     Lit(Lit),
     Ident(InternedString),
+    Path(Vec<InternedString>),
+    Apply(Expr, Vec<Expr>),
+    If(Expr, Expr, Expr),
     Let(Pattern, Expr, Expr),
+    // like `Let`, but the binding is also in scope in its own expression so
+    // a bound lambda can recurse; named `let` and `letf` lower to this
+    Letrec(Pattern, Expr, Expr),
     Match(Expr, Vec<MatchArm>),
+    // the parameter pattern matches the list of arguments
     Lambda(Pattern, Expr),
+    // evaluates each expression in turn and yields the last
+    Do(Vec<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Quote(Sexpr),
+    Quasiquote(Template),
     List(List<Expr>),
     Vector(Vec<Expr>),
     Map(Vec<(Expr, Expr)>),
+    Set(Vec<Expr>),
 }
 
+// (pattern body...) or (pattern when guard body...)
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchArm {
-    pat: Pattern,
-    expr: Expr,
-    span: Span,
+    pub pat: Pattern,
+    pub guard: Option<Expr>,
+    pub expr: Expr,
+    pub span: Span,
+}
+
+impl MatchArm {
+    pub fn new(pat: Pattern, guard: Option<Expr>, expr: Expr, span: Span) -> Self {
+        Self {
+            pat,
+            guard,
+            expr,
+            span,
+        }
+    }
+}
+
+// A quasiquoted datum with unquoted holes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub kind: Box<TemplateKind>,
+    pub span: Span,
+}
+
+impl Template {
+    pub fn new(kind: TemplateKind, span: Span) -> Self {
+        Self {
+            kind: Box::new(kind),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateKind {
+    // a part without any unquotes, taken literally
+    Datum(Sexpr),
+    Unquote(Expr),
+    UnquoteSplicing(Expr),
+    List(Vec<Template>, Option<Template>),
+    DataList(Vec<Template>),
+    Vector(Vec<Template>),
+    Map(Vec<(Template, Template)>),
+    Set(Vec<Template>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -65,9 +134,23 @@ impl Pattern {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum PatternKind {
+    // `_`, matches anything without binding it
+    Wildcard,
+    // literals, including keywords like `:ok`
     Lit(Lit),
     Ident(InternedString),
+    // `(x . xs)` and `(x xs...)` bind the rest of the list to `xs`
     List(List<Pattern>),
+    // `#[x y]` and `#[x ys...]`
+    Vector(Vec<Pattern>, Option<Pattern>),
+    // `{:key pattern}` matches maps and records with at least those keys
+    Map(Vec<(Lit, Pattern)>),
+    // `(as pattern name)` binds the whole value to `name` as well
+    As(Pattern, InternedString),
+    // `(or p1 p2...)`; every alternative binds the same names
+    Or(Vec<Pattern>),
+    // `'a` or `'(1 2)` matches values equal to the datum and binds nothing
+    Quote(Sexpr),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -80,4 +163,6 @@ pub enum Lit {
     String(InternedString),
     Bool(bool),
     Char(char),
+    Bytes(Vec<u8>),
+    Keyword(InternedString),
 }
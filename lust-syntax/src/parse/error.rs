@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use lust_utils::span::Span;
+use lust_utils::{diagnostic::Diagnostic, span::Span};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
@@ -24,4 +24,12 @@ impl Display for ParseError {
     }
 }
 
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        Diagnostic::error(err.msg)
+            .with_code("E0100")
+            .with_span(err.span)
+    }
+}
+
 pub type ParseResult<T> = std::result::Result<T, ParseError>;
@@ -2,8 +2,11 @@ use self::{
     ast::*,
     error::{ParseError, ParseResult},
 };
-use crate::read::sexpr::{self, AtomKind, Sexpr, SexprKind};
-use lust_utils::list::List;
+use crate::read::{
+    sexpr::{self, AtomKind, Sexpr, SexprKind},
+    visit::{walk_sexpr, Visitor},
+};
+use lust_utils::{intern::InternedString, list::List, span::Span};
 
 pub mod ast;
 pub mod error;
@@ -11,104 +14,818 @@ pub mod error;
 pub fn parse(root: sexpr::Root) -> (Option<ast::Root>, Vec<ParseError>) {
     let mut items = vec![];
     let mut errs = vec![];
-    for sexpr in root.sexprs {
-        match parse_def(sexpr) {
-            Ok(decl) => items.push(decl),
+    let mut sexprs = root.sexprs.as_slice();
+    while !sexprs.is_empty() {
+        let n = clause_count(sexprs);
+        let item = match n {
+            1 => parse_item(&sexprs[0]),
+            _ => parse_clauses(&sexprs[..n]).map(Item::Def),
+        };
+        match item {
+            Ok(item) => items.push(item),
             Err(err) => errs.push(err),
         }
+        sexprs = &sexprs[n..];
     }
-    (Some(ast::Root {defs, span: root.span}), errs)
+    (
+        Some(ast::Root {
+            items,
+            span: root.span,
+        }),
+        errs,
+    )
 }
 
-fn parse_def(sexpr: &Sexpr) -> ParseResult<Decl> {
-    match sexpr.kind() {
-        SexprKind::List(list) => {
-            let head = list.head().ok_or(ParseError::new(
-                "expected first element".to_string(),
-                sexpr.span(),
-            ))?;
-
-            match head.kind() {
-                SexprKind::Atom(a) => match a.kind() {
-                    AtomKind::Sym(s) => match s.as_ref() {
-                        "def" => {
-                            let mut iter = list.iter();
-                            iter.next(); // skip head
-                            let name = match iter
-                                .next()
-                                .ok_or(ParseError::new("expected name".to_string(), head.span()))?.kind() {
-                                SexprKind::Atom(a) => match a.kind() {
-                                    AtomKind::Sym(s) => s.clone(),
-                                    _ => {
-                                        return Err(ParseError::new(
-                                            "expected symbol".to_string(),
-                                            head.span(),
-                                        ))
-                                    }
-                                },
-                                _ => {
-                                    return Err(ParseError::new(
-                                        "expected atom".to_string(),
-                                        head.span(),
-                                    ))
-                                }
-                            let expr = iter.next().ok_or(ParseError::new(
-                                "expected expression".to_string(),
-                                head.span(),
-                            ))?;
-                            Ok(Decl::new(
-                                DeclKind::Def {
-                                    name: name,
-                                    expr: parse_expr(expr)?,
-                                },
-                                sexpr.span(),
-                            ))
-                        }
-                        _ => Err(ParseError::new(
-                            format!("unexpected special form: {}", s),
-                            head.span(),
-                        )),
-                    },
-                    _ => Err(ParseError::new("expected symbol".to_string(), head.span())),
-                },
-                _ => Err(ParseError::new("expected atom".to_string(), head.span())),
+fn parse_item(sexpr: &Sexpr) -> ParseResult<Item> {
+    match sexpr.as_special_form() {
+        Some("def") => parse_def(sexpr).map(Item::Def),
+        _ => parse_expr(sexpr).map(Item::Expr),
+    }
+}
+
+fn parse_def(sexpr: &Sexpr) -> ParseResult<Def> {
+    let span = sexpr.span();
+    match form_args(sexpr)?.as_slice() {
+        [header, body @ ..] if !body.is_empty() && matches!(header.kind(), SexprKind::List(_)) => {
+            parse_fn_def(header, body, span)
+        }
+        [pat, expr] => Ok(Def {
+            pat: parse_pattern(pat)?,
+            expr: parse_expr(expr)?,
+            doc: None,
+            span,
+        }),
+        _ => Err(ParseError::new(
+            "expected `(def pattern expr)` or `(def (name params...) body...)`".to_string(),
+            span,
+        )),
+    }
+}
+
+// (def (f x . xs) "doc" body...) -> (def f (fn (x . xs) body...))
+//
+// A leading string is only a docstring when more body expressions follow,
+// so `(def (f) "s")` still returns "s".
+fn parse_fn_def(header: &Sexpr, body: &[&Sexpr], span: Span) -> ParseResult<Def> {
+    let (name, params) = parse_fn_header(header)?;
+    let (doc, body) = match body {
+        [doc, rest @ ..] if !rest.is_empty() => match as_string(doc) {
+            Some(doc) => (Some(doc), rest),
+            None => (None, body),
+        },
+        _ => (None, body),
+    };
+    Ok(Def {
+        pat: name,
+        expr: Expr::new(ExprKind::Lambda(params, parse_body(body)?), span),
+        doc,
+        span,
+    })
+}
+
+// (name params...) -> `name` and the parameter list pattern
+fn parse_fn_header(header: &Sexpr) -> ParseResult<(Pattern, Pattern)> {
+    let SexprKind::List(list) = header.kind() else {
+        return Err(ParseError::new(
+            "expected a `(name params...)` header".to_string(),
+            header.span(),
+        ));
+    };
+    let name = match list.head() {
+        Some(name) => match name.as_atom().and_then(|a| a.as_sym()) {
+            Some(sym) => Pattern::new(PatternKind::Ident(sym), name.span()),
+            None => {
+                return Err(ParseError::new(
+                    "expected a function name".to_string(),
+                    name.span(),
+                ))
             }
-                SexprKind::List(_) => todo!(),
+        },
+        None => {
+            return Err(ParseError::new(
+                "expected a function name".to_string(),
+                header.span(),
+            ))
         }
-        _ => Err(ParseError::new("expected list".to_string(), sexpr.span())),
+    };
+    let params = parse_list_pattern(
+        list.iter().skip(1).collect(),
+        list.dotted_tail(),
+        header.span(),
+    )?;
+    check_binders(&params)?;
+    Ok((name, params))
+}
+
+// Consecutive definitions of the same function form one multi-clause
+// function when any of their parameters is a literal or a nested pattern:
+//
+// (def (fib 0) 0)
+// (def (fib 1) 1)
+// (def (fib n) (+ (fib (- n 1)) (fib (- n 2))))
+//
+// Runs where every parameter is a plain name are redefinitions instead.
+// Returns how many sexprs the next item takes up.
+fn clause_count(sexprs: &[Sexpr]) -> usize {
+    let Some((name, _)) = fn_header(&sexprs[0]) else {
+        return 1;
+    };
+    let headers = sexprs
+        .iter()
+        .map_while(|sexpr| fn_header(sexpr).filter(|(n, _)| *n == name))
+        .map(|(_, header)| header)
+        .collect::<Vec<_>>();
+    let is_equation = |header: &List<Sexpr>| {
+        header
+            .iter()
+            .skip(1)
+            .map(|param| as_varg(param).unwrap_or(param))
+            .chain(header.dotted_tail())
+            .any(|param| param.as_atom().and_then(|a| a.as_sym()).is_none())
+    };
+    if headers.len() > 1 && headers.iter().any(|header| is_equation(header)) {
+        headers.len()
+    } else {
+        1
     }
-        SexprKind::Atom(_) => todo!(),
-}}
+}
+
+// `(def (name params...) body...)` -> `name` and the header
+fn fn_header(sexpr: &Sexpr) -> Option<(InternedString, &List<Sexpr>)> {
+    if sexpr.as_special_form() != Some("def") {
+        return None;
+    }
+    match form_args(sexpr).ok()?.as_slice() {
+        [header, _, ..] => match header.kind() {
+            SexprKind::List(list) => Some((list.head()?.as_atom()?.as_sym()?, list)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// (def (f p1 p2) b1) (def (f q1 q2) b2)
+// -> (def f (fn #args (match #args ((p1 p2) b1) ((q1 q2) b2))))
+//
+// `#args` can't be written in source, so it never captures a name used
+// in a clause body.
+fn parse_clauses(clauses: &[Sexpr]) -> ParseResult<Def> {
+    let span = clauses[0].span().extend(clauses[clauses.len() - 1].span());
+    let mut name = None;
+    let mut doc = None;
+    let mut arity = None;
+    let mut arms = vec![];
+    for clause in clauses {
+        let def = parse_def(clause)?;
+        let ExprKind::Lambda(params, body) = *def.expr.kind else {
+            unreachable!("function definitions are lowered to lambdas")
+        };
+        let PatternKind::List(list) = &*params.kind else {
+            unreachable!("function parameters are list patterns")
+        };
+        let clause_arity = (list.iter().count(), list.dotted_tail().is_some());
+        match arity {
+            None => arity = Some(clause_arity),
+            Some(first) if first != clause_arity => {
+                return Err(ParseError::new(
+                    format!(
+                        "this clause of `{}` takes {}, but the first clause takes {}",
+                        clauses_name(&def.pat),
+                        fmt_arity(clause_arity),
+                        fmt_arity(first)
+                    ),
+                    params.span,
+                ))
+            }
+            Some(_) => {}
+        }
+        name.get_or_insert(def.pat);
+        doc = doc.or(def.doc);
+        arms.push(MatchArm::new(params, None, body, def.span));
+    }
+    let args = InternedString::from("#args");
+    let name = name.unwrap();
+    Ok(Def {
+        expr: Expr::new(
+            ExprKind::Lambda(
+                Pattern::new(PatternKind::Ident(args), name.span),
+                Expr::new(
+                    ExprKind::Match(Expr::new(ExprKind::Ident(args), name.span), arms),
+                    span,
+                ),
+            ),
+            span,
+        ),
+        pat: name,
+        doc,
+        span,
+    })
+}
+
+fn clauses_name(pat: &Pattern) -> InternedString {
+    match &*pat.kind {
+        PatternKind::Ident(name) => *name,
+        _ => unreachable!("function names are identifiers"),
+    }
+}
+
+fn fmt_arity((n, rest): (usize, bool)) -> String {
+    let plural = if n == 1 { "" } else { "s" };
+    if rest {
+        format!("at least {} argument{}", n, plural)
+    } else {
+        format!("{} argument{}", n, plural)
+    }
+}
 
 fn parse_expr(sexpr: &Sexpr) -> ParseResult<Expr> {
     match sexpr.kind() {
         sexpr::SexprKind::Atom(a) => match a.kind() {
             sexpr::AtomKind::Lit(l) => Ok(Expr::new(ExprKind::Lit(parse_lit(l)), sexpr.span())),
-            sexpr::AtomKind::Sym(name) => {
-                Ok(Expr::new(ExprKind::Ident(name.clone()), sexpr.span()))
+            sexpr::AtomKind::Sym(name) => Ok(Expr::new(ExprKind::Ident(*name), sexpr.span())),
+            sexpr::AtomKind::Keyword(k) => {
+                Ok(Expr::new(ExprKind::Lit(Lit::Keyword(*k)), sexpr.span()))
+            }
+            sexpr::AtomKind::Path(p) => Ok(Expr::new(ExprKind::Path(p.clone()), sexpr.span())),
+        },
+        sexpr::SexprKind::List(l) => match sexpr.as_special_form() {
+            Some(form) => parse_special_form(form, sexpr),
+            None => {
+                let args = form_args(sexpr)?;
+                let fun = l.head().ok_or(ParseError::new(
+                    "expected a function or special form; use `[]` for the empty list".to_string(),
+                    sexpr.span(),
+                ))?;
+                Ok(Expr::new(
+                    ExprKind::Apply(
+                        parse_expr(fun)?,
+                        args.into_iter()
+                            .map(parse_expr)
+                            .collect::<ParseResult<_>>()?,
+                    ),
+                    sexpr.span(),
+                ))
             }
         },
-        sexpr::SexprKind::List(l) => {
-            let first = l.head().ok_or(ParseError::new(
-                "expected first element".to_string(),
-                sexpr.span(),
-            ))?;
-            // handle special forms
-            todo!()
+        sexpr::SexprKind::DataList(v) => Ok(Expr::new(
+            ExprKind::List(List::from(
+                v.iter().map(parse_expr).collect::<ParseResult<Vec<_>>>()?,
+            )),
+            sexpr.span(),
+        )),
+        sexpr::SexprKind::Vector(v) => Ok(Expr::new(
+            ExprKind::Vector(v.iter().map(parse_expr).collect::<ParseResult<_>>()?),
+            sexpr.span(),
+        )),
+        sexpr::SexprKind::Map(m) => Ok(Expr::new(
+            ExprKind::Map(
+                m.iter()
+                    .map(|(k, v)| Ok((parse_expr(k)?, parse_expr(v)?)))
+                    .collect::<ParseResult<_>>()?,
+            ),
+            sexpr.span(),
+        )),
+        sexpr::SexprKind::Set(s) => Ok(Expr::new(
+            ExprKind::Set(s.iter().map(parse_expr).collect::<ParseResult<_>>()?),
+            sexpr.span(),
+        )),
+    }
+}
+
+fn parse_special_form(form: &str, sexpr: &Sexpr) -> ParseResult<Expr> {
+    let span = sexpr.span();
+    let malformed = |shape: &str| ParseError::new(format!("expected `{}`", shape), span);
+    let kind = match (form, form_args(sexpr)?.as_slice()) {
+        ("def", _) => {
+            return Err(ParseError::new(
+                "`def` is only allowed at the top level".to_string(),
+                span,
+            ))
+        }
+        ("fn", [params, body @ ..]) if !body.is_empty() => {
+            ExprKind::Lambda(parse_pattern(params)?, parse_body(body)?)
+        }
+        ("fn", _) => return Err(malformed("(fn params body...)")),
+        ("let", [name, rest @ ..]) if name.as_atom().is_some_and(|a| a.as_sym().is_some()) => {
+            return match rest {
+                [bindings, body @ ..] if !body.is_empty() => {
+                    parse_named_let(name, bindings, body, span)
+                }
+                _ => Err(malformed("(let name ((pattern expr)...) body...)")),
+            };
         }
+        ("let", [bindings, body @ ..]) if !body.is_empty() => {
+            return parse_let(bindings, body, span)
+        }
+        ("let", _) => return Err(malformed("(let ((pattern expr)...) body...)")),
+        ("letf", [header, fbody, body @ ..]) if !body.is_empty() => {
+            let (name, params) = parse_fn_header(header)?;
+            let lambda = Expr::new(
+                ExprKind::Lambda(params, parse_expr(fbody)?),
+                header.span().extend(fbody.span()),
+            );
+            ExprKind::Letrec(name, lambda, parse_body(body)?)
+        }
+        ("letf", _) => return Err(malformed("(letf (name params...) fbody body...)")),
+        ("if", [cond, then, else_]) => {
+            ExprKind::If(parse_expr(cond)?, parse_expr(then)?, parse_expr(else_)?)
+        }
+        ("if", _) => return Err(malformed("(if cond then else)")),
+        ("match", [expr, arms @ ..]) => ExprKind::Match(
+            parse_expr(expr)?,
+            arms.iter()
+                .map(|arm| parse_arm(arm))
+                .collect::<ParseResult<_>>()?,
+        ),
+        ("match", _) => return Err(malformed("(match expr (pattern body...)...)")),
+        ("and", args) => ExprKind::And(
+            args.iter()
+                .map(|e| parse_expr(e))
+                .collect::<ParseResult<_>>()?,
+        ),
+        ("or", args) => ExprKind::Or(
+            args.iter()
+                .map(|e| parse_expr(e))
+                .collect::<ParseResult<_>>()?,
+        ),
+        ("quote", [datum]) => ExprKind::Quote((*datum).clone()),
+        ("quote", _) => return Err(malformed("(quote datum)")),
+        ("quasiquote", [datum]) => ExprKind::Quasiquote(parse_template_part(datum, 1)?),
+        ("quasiquote", _) => return Err(malformed("(quasiquote datum)")),
+        (form, _) => {
+            return Err(ParseError::new(
+                format!("`{}` outside of a quasiquote", form),
+                span,
+            ))
+        }
+    };
+    Ok(Expr::new(kind, span))
+}
+
+// The elements after the head of a form, which can't have a dotted tail.
+fn form_args(sexpr: &Sexpr) -> ParseResult<Vec<&Sexpr>> {
+    let SexprKind::List(list) = sexpr.kind() else {
+        return Err(ParseError::new("expected list".to_string(), sexpr.span()));
+    };
+    if let Some(tail) = list.dotted_tail() {
+        return Err(ParseError::new(
+            "dotted lists can't be evaluated".to_string(),
+            tail.span(),
+        ));
+    }
+    Ok(list.iter().skip(1).collect())
+}
+
+// body...; several expressions are evaluated in sequence
+fn parse_body(body: &[&Sexpr]) -> ParseResult<Expr> {
+    match body {
+        [expr] => parse_expr(expr),
+        _ => {
+            let span = body[0].span().extend(body[body.len() - 1].span());
+            Ok(Expr::new(
+                ExprKind::Do(
+                    body.iter()
+                        .map(|e| parse_expr(e))
+                        .collect::<ParseResult<_>>()?,
+                ),
+                span,
+            ))
+        }
+    }
+}
+
+// (let ((p1 e1) (p2 e2)) body...) -> (let p1 e1 (let p2 e2 body))
+fn parse_let(bindings: &Sexpr, body: &[&Sexpr], span: Span) -> ParseResult<Expr> {
+    let bindings = parse_bindings(bindings)?;
+    let body = parse_body(body)?;
+    Ok(bindings
+        .into_iter()
+        .rev()
+        .fold(body, |body, (pat, expr, binding_span)| {
+            Expr::new(
+                ExprKind::Let(pat, expr, body),
+                Span::new(binding_span.start(), span.end()),
+            )
+        }))
+}
+
+// (let loop ((p1 e1) (p2 e2)) body...)
+// -> ((letrec loop (fn (p1 p2) body...) loop) e1 e2)
+//
+// The initial values are evaluated outside the loop, so they can't see
+// `loop` themselves.
+fn parse_named_let(
+    name: &Sexpr,
+    bindings: &Sexpr,
+    body: &[&Sexpr],
+    span: Span,
+) -> ParseResult<Expr> {
+    let Some(sym) = name.as_atom().and_then(|a| a.as_sym()) else {
+        unreachable!("loop names are symbols")
+    };
+    let (params, inits): (Vec<_>, Vec<_>) = parse_bindings(bindings)?
+        .into_iter()
+        .map(|(pat, expr, _)| (pat, expr))
+        .unzip();
+    let params = Pattern::new(
+        PatternKind::List(List::from_parts(params, None)),
+        bindings.span(),
+    );
+    check_binders(&params)?;
+    let lambda = Expr::new(ExprKind::Lambda(params, parse_body(body)?), span);
+    let letrec = Expr::new(
+        ExprKind::Letrec(
+            Pattern::new(PatternKind::Ident(sym), name.span()),
+            lambda,
+            Expr::new(ExprKind::Ident(sym), name.span()),
+        ),
+        span,
+    );
+    Ok(Expr::new(ExprKind::Apply(letrec, inits), span))
+}
+
+// ((p1 e1) (p2 e2) ...)
+fn parse_bindings(bindings: &Sexpr) -> ParseResult<Vec<(Pattern, Expr, Span)>> {
+    let bindings: Vec<&Sexpr> = match bindings.kind() {
+        SexprKind::List(l) if l.is_proper() => l.iter().collect(),
+        SexprKind::DataList(v) => v.iter().collect(),
+        _ => {
+            return Err(ParseError::new(
+                "expected a list of `(pattern expr)` bindings".to_string(),
+                bindings.span(),
+            ))
+        }
+    };
+    bindings
+        .into_iter()
+        .map(|binding| match as_pair(binding) {
+            Some((pat, expr)) => Ok((parse_pattern(pat)?, parse_expr(expr)?, binding.span())),
+            None => Err(ParseError::new(
+                "expected a `(pattern expr)` binding".to_string(),
+                binding.span(),
+            )),
+        })
+        .collect()
+}
+
+// (pattern body...) or (pattern when guard body...)
+fn parse_arm(arm: &Sexpr) -> ParseResult<MatchArm> {
+    let items = match arm.kind() {
+        SexprKind::List(l) if l.is_proper() => l.iter().collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    match items.as_slice() {
+        [pat, when, guard, body @ ..] if is_sym(when, "when") && !body.is_empty() => {
+            Ok(MatchArm::new(
+                parse_pattern(pat)?,
+                Some(parse_expr(guard)?),
+                parse_body(body)?,
+                arm.span(),
+            ))
+        }
+        [_, when, ..] if is_sym(when, "when") => Err(ParseError::new(
+            "expected a `(pattern when guard body...)` match arm".to_string(),
+            arm.span(),
+        )),
+        [pat, body @ ..] if !body.is_empty() => Ok(MatchArm::new(
+            parse_pattern(pat)?,
+            None,
+            parse_body(body)?,
+            arm.span(),
+        )),
+        _ => Err(ParseError::new(
+            "expected a `(pattern body...)` match arm".to_string(),
+            arm.span(),
+        )),
+    }
+}
+
+fn as_pair(sexpr: &Sexpr) -> Option<(&Sexpr, &Sexpr)> {
+    let SexprKind::List(list) = sexpr.kind() else {
+        return None;
+    };
+    let mut iter = list.iter();
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(a), Some(b), None) if list.is_proper() => Some((a, b)),
+        _ => None,
+    }
+}
+
+// A quasiquoted datum `depth` quasiquotes deep; unquotes at depth 1 are
+// code, deeper ones stay part of the template.
+fn parse_template(sexpr: &Sexpr, depth: usize) -> ParseResult<Template> {
+    let span = sexpr.span();
+    if !has_unquote(sexpr) {
+        return Ok(Template::new(TemplateKind::Datum(sexpr.clone()), span));
+    }
+    let kind = match sexpr.kind() {
+        SexprKind::Atom(_) => TemplateKind::Datum(sexpr.clone()),
+        SexprKind::List(list) => {
+            let head = list
+                .head()
+                .and_then(|h| h.as_atom())
+                .and_then(|a| a.as_sym());
+            let depth = match head.as_deref() {
+                Some(form @ ("unquote" | "unquote-splicing")) if depth == 1 => {
+                    let [arg] = form_args(sexpr)?[..] else {
+                        return Err(ParseError::new(format!("expected `({} expr)`", form), span));
+                    };
+                    let expr = parse_expr(arg)?;
+                    let kind = if form == "unquote" {
+                        TemplateKind::Unquote(expr)
+                    } else {
+                        TemplateKind::UnquoteSplicing(expr)
+                    };
+                    return Ok(Template::new(kind, span));
+                }
+                Some("unquote" | "unquote-splicing") => depth - 1,
+                Some("quasiquote") => depth + 1,
+                _ => depth,
+            };
+            TemplateKind::List(
+                list.iter()
+                    .map(|s| parse_template(s, depth))
+                    .collect::<ParseResult<_>>()?,
+                list.dotted_tail()
+                    .map(|tail| parse_template_part(tail, depth))
+                    .transpose()?,
+            )
+        }
+        SexprKind::DataList(v) => TemplateKind::DataList(parse_template_seq(v, depth)?),
+        SexprKind::Vector(v) => TemplateKind::Vector(parse_template_seq(v, depth)?),
+        SexprKind::Set(v) => TemplateKind::Set(parse_template_seq(v, depth)?),
+        SexprKind::Map(m) => TemplateKind::Map(
+            m.iter()
+                .map(|(k, v)| {
+                    Ok((
+                        parse_template_part(k, depth)?,
+                        parse_template_part(v, depth)?,
+                    ))
+                })
+                .collect::<ParseResult<_>>()?,
+        ),
+    };
+    Ok(Template::new(kind, span))
+}
+
+fn parse_template_seq(sexprs: &[Sexpr], depth: usize) -> ParseResult<Vec<Template>> {
+    sexprs.iter().map(|s| parse_template(s, depth)).collect()
+}
+
+// A template that isn't an element of a sequence, so it can't be spliced.
+fn parse_template_part(sexpr: &Sexpr, depth: usize) -> ParseResult<Template> {
+    let template = parse_template(sexpr, depth)?;
+    match *template.kind {
+        TemplateKind::UnquoteSplicing(_) => Err(ParseError::new(
+            "`unquote-splicing` must be an element of a list".to_string(),
+            template.span,
+        )),
+        _ => Ok(template),
     }
 }
 
+fn has_unquote(sexpr: &Sexpr) -> bool {
+    struct HasUnquote(bool);
+
+    impl Visitor for HasUnquote {
+        fn visit_sexpr(&mut self, sexpr: &Sexpr) {
+            match sexpr.as_special_form() {
+                Some("unquote" | "unquote-splicing") => self.0 = true,
+                _ => walk_sexpr(self, sexpr),
+            }
+        }
+    }
+
+    let mut visitor = HasUnquote(false);
+    visitor.visit_sexpr(sexpr);
+    visitor.0
+}
+
 fn parse_pattern(sexpr: &Sexpr) -> ParseResult<Pattern> {
-    match sexpr.kind() {
+    let pat = parse_subpattern(sexpr)?;
+    check_binders(&pat)?;
+    Ok(pat)
+}
+
+fn parse_subpattern(sexpr: &Sexpr) -> ParseResult<Pattern> {
+    let span = sexpr.span();
+    let kind = match sexpr.kind() {
         SexprKind::Atom(a) => match a.kind() {
-            AtomKind::Sym(s) => Ok(Pattern::new(PatternKind::Ident(s.clone()), sexpr.span())),
-            AtomKind::Lit(l) => Ok(Pattern::new(PatternKind::Lit(parse_lit(l)), sexpr.span())),
+            AtomKind::Sym(s) if &**s == "_" => PatternKind::Wildcard,
+            AtomKind::Sym(s) => PatternKind::Ident(*s),
+            AtomKind::Lit(l) => PatternKind::Lit(parse_lit(l)),
+            AtomKind::Keyword(k) => PatternKind::Lit(Lit::Keyword(*k)),
+            AtomKind::Path(_) => {
+                return Err(ParseError::new(
+                    "paths can't be bound in patterns".to_string(),
+                    span,
+                ))
+            }
         },
-        SexprKind::List(list) => {
-            // parse list sexprs as patterns
-            list.iter().map(|s| parse_pattern(s)).collect::<ParseResult<_>>()?
+        SexprKind::List(list) => match list.head() {
+            Some(head) if is_sym(head, "as") => match form_args(sexpr)?.as_slice() {
+                [pat, name] => match name.as_atom().and_then(|a| a.as_sym()) {
+                    Some(name) if &*name != "_" => PatternKind::As(parse_subpattern(pat)?, name),
+                    _ => {
+                        return Err(ParseError::new(
+                            "expected a name to bind".to_string(),
+                            name.span(),
+                        ))
+                    }
+                },
+                _ => {
+                    return Err(ParseError::new(
+                        "expected `(as pattern name)`".to_string(),
+                        span,
+                    ))
+                }
+            },
+            Some(head) if is_sym(head, "quote") => match form_args(sexpr)?.as_slice() {
+                [datum] => PatternKind::Quote((*datum).clone()),
+                _ => {
+                    return Err(ParseError::new(
+                        "expected `(quote datum)`".to_string(),
+                        span,
+                    ))
+                }
+            },
+            Some(head) if is_sym(head, "or") => {
+                let alts = form_args(sexpr)?;
+                if alts.is_empty() {
+                    return Err(ParseError::new(
+                        "expected `(or pattern...)`".to_string(),
+                        span,
+                    ));
+                }
+                PatternKind::Or(
+                    alts.into_iter()
+                        .map(parse_subpattern)
+                        .collect::<ParseResult<_>>()?,
+                )
+            }
+            _ => return parse_list_pattern(list.iter().collect(), list.dotted_tail(), span),
+        },
+        SexprKind::DataList(v) => return parse_list_pattern(v.iter().collect(), None, span),
+        SexprKind::Vector(v) => {
+            let (items, rest) = split_rest(v.iter().collect(), None)?;
+            PatternKind::Vector(items, rest)
+        }
+        SexprKind::Map(m) => PatternKind::Map(
+            m.iter()
+                .map(|(key, pat)| match key.as_atom().map(|a| a.kind().clone()) {
+                    Some(AtomKind::Lit(l)) => Ok((parse_lit(&l), parse_subpattern(pat)?)),
+                    Some(AtomKind::Keyword(k)) => Ok((Lit::Keyword(k), parse_subpattern(pat)?)),
+                    _ => Err(ParseError::new(
+                        "map pattern keys must be literals".to_string(),
+                        key.span(),
+                    )),
+                })
+                .collect::<ParseResult<_>>()?,
+        ),
+        SexprKind::Set(_) => {
+            return Err(ParseError::new(
+                "set patterns are not supported".to_string(),
+                span,
+            ))
+        }
+    };
+    Ok(Pattern::new(kind, span))
+}
+
+// The rest of a list is bound either with a dotted tail, `(x . xs)`, or
+// with the variadic sugar, `(x xs...)`, which reads as `(x (varg xs))`.
+fn parse_list_pattern(
+    items: Vec<&Sexpr>,
+    tail: Option<&Sexpr>,
+    span: Span,
+) -> ParseResult<Pattern> {
+    let (items, rest) = split_rest(items, tail)?;
+    Ok(Pattern::new(
+        PatternKind::List(List::from_parts(items, rest)),
+        span,
+    ))
+}
+
+fn split_rest(
+    mut items: Vec<&Sexpr>,
+    tail: Option<&Sexpr>,
+) -> ParseResult<(Vec<Pattern>, Option<Pattern>)> {
+    let mut rest = tail;
+    if let Some(varg) = items.last().and_then(|last| as_varg(last)) {
+        if rest.is_some() {
+            return Err(ParseError::new(
+                "a list pattern can only have one rest binding".to_string(),
+                items[items.len() - 1].span(),
+            ));
+        }
+        rest = Some(varg);
+        items.pop();
+    }
+    if let Some(varg) = items.iter().find(|item| as_varg(item).is_some()) {
+        return Err(ParseError::new(
+            "the rest binding must come last".to_string(),
+            varg.span(),
+        ));
+    }
+    let items = items
+        .into_iter()
+        .map(parse_subpattern)
+        .collect::<ParseResult<Vec<_>>>()?;
+    let rest = rest.map(parse_subpattern).transpose()?;
+    Ok((items, rest))
+}
+
+// Reports names bound twice in one pattern, and or-patterns whose
+// alternatives bind different names.
+fn check_binders(pat: &Pattern) -> ParseResult<()> {
+    let mut binders = vec![];
+    collect_binders(pat, &mut binders)?;
+    for (i, (name, span)) in binders.iter().enumerate() {
+        if binders[..i].iter().any(|(prev, _)| prev == name) {
+            return Err(ParseError::new(
+                format!("`{}` is bound more than once in this pattern", name),
+                *span,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn collect_binders(pat: &Pattern, binders: &mut Vec<(InternedString, Span)>) -> ParseResult<()> {
+    match &*pat.kind {
+        PatternKind::Wildcard | PatternKind::Lit(_) | PatternKind::Quote(_) => {}
+        PatternKind::Ident(name) => binders.push((*name, pat.span)),
+        PatternKind::List(list) => {
+            for p in list.iter().chain(list.dotted_tail()) {
+                collect_binders(p, binders)?;
+            }
+        }
+        PatternKind::Vector(items, rest) => {
+            for p in items.iter().chain(rest) {
+                collect_binders(p, binders)?;
+            }
+        }
+        PatternKind::Map(entries) => {
+            for (_, p) in entries {
+                collect_binders(p, binders)?;
+            }
+        }
+        PatternKind::As(p, name) => {
+            collect_binders(p, binders)?;
+            binders.push((*name, pat.span));
+        }
+        PatternKind::Or(alts) => {
+            let mut first = vec![];
+            collect_binders(&alts[0], &mut first)?;
+            check_binders(&alts[0])?;
+            for alt in &alts[1..] {
+                check_binders(alt)?;
+                let mut other = vec![];
+                collect_binders(alt, &mut other)?;
+                let same = other.len() == first.len()
+                    && other
+                        .iter()
+                        .all(|(name, _)| first.iter().any(|(n, _)| n == name));
+                if !same {
+                    return Err(ParseError::new(
+                        "every alternative of an or-pattern must bind the same names".to_string(),
+                        alt.span,
+                    ));
+                }
+            }
+            binders.extend(first);
+        }
+    }
+    Ok(())
+}
+
+fn is_sym(sexpr: &Sexpr, name: &str) -> bool {
+    sexpr
+        .as_atom()
+        .and_then(|a| a.as_sym())
+        .is_some_and(|s| &*s == name)
+}
+
+fn as_string(sexpr: &Sexpr) -> Option<InternedString> {
+    match sexpr.as_atom()?.kind() {
+        AtomKind::Lit(sexpr::Lit::String(s)) => Some(*s),
+        _ => None,
+    }
+}
+
+// `(varg xs)` -> `xs`
+fn as_varg(sexpr: &Sexpr) -> Option<&Sexpr> {
+    let SexprKind::List(list) = sexpr.kind() else {
+        return None;
+    };
+    let mut iter = list.iter();
+    match (iter.next()?.kind(), iter.next(), iter.next()) {
+        (SexprKind::Atom(a), Some(datum), None)
+            if list.is_proper() && a.as_sym().is_some_and(|s| &*s == "varg") =>
+        {
+            Some(datum)
         }
+        _ => None,
     }
 }
 
@@ -122,5 +839,401 @@ fn parse_lit(lit: &sexpr::Lit) -> Lit {
         sexpr::Lit::String(s) => Lit::String(s),
         sexpr::Lit::Bool(b) => Lit::Bool(b),
         sexpr::Lit::Char(c) => Lit::Char(c),
+        sexpr::Lit::Bytes(b) => Lit::Bytes(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ast::*, error::ParseError, parse};
+    use crate::read::read;
+
+    fn parse_src(src: &str) -> (Vec<Item>, Vec<ParseError>) {
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        let (root, errs) = parse(root);
+        (root.unwrap().items, errs)
+    }
+
+    fn expr(src: &str) -> ExprKind {
+        let (items, errs) = parse_src(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        match items.into_iter().next() {
+            Some(Item::Expr(expr)) => *expr.kind,
+            item => panic!("expected an expression, got {:?}", item),
+        }
+    }
+
+    fn err(src: &str) -> (String, u32, u32) {
+        let (_, errs) = parse_src(src);
+        let [err] = &errs[..] else {
+            panic!("expected one error, got {:?}", errs);
+        };
+        let msg = err.to_string();
+        let msg = msg.split_once(": ").unwrap().1.to_string();
+        (msg, err.span().start(), err.span().end())
+    }
+
+    #[test]
+    fn parse_application() {
+        let ExprKind::Apply(fun, args) = expr("(add 1 2)") else {
+            panic!("expected application");
+        };
+        assert_eq!(*fun.kind, ExprKind::Ident("add".into()));
+        assert_eq!(args.len(), 2);
+        assert!(matches!(expr("((fn (x) (+ x 1)) 1)"), ExprKind::Apply(..)));
+        assert!(matches!(expr("(m.a)"), ExprKind::Apply(_, args) if args.is_empty()));
+    }
+
+    #[test]
+    fn parse_special_forms() {
+        let ExprKind::Lambda(param, body) = expr("(fn (x . xs) (print x) xs)") else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(*param.kind, PatternKind::List(_)));
+        assert!(matches!(&*body.kind, ExprKind::Do(exprs) if exprs.len() == 2));
+
+        let ExprKind::Let(_, _, body) = expr("(let ((x 10) (y 20)) (+ x y))") else {
+            panic!("expected let");
+        };
+        assert!(matches!(*body.kind, ExprKind::Let(..)));
+        assert!(matches!(expr("(if c 1 2)"), ExprKind::If(..)));
+        assert!(matches!(expr("(and true false)"), ExprKind::And(v) if v.len() == 2));
+        assert!(matches!(expr("(or)"), ExprKind::Or(v) if v.is_empty()));
+        assert!(matches!(expr("'(+ 1 2)"), ExprKind::Quote(_)));
+        let ExprKind::Match(_, arms) = expr("(match n (0 0) (n (f n) n))") else {
+            panic!("expected match");
+        };
+        assert_eq!(arms.len(), 2);
+        assert!(matches!(&*arms[1].expr.kind, ExprKind::Do(_)));
+    }
+
+    #[test]
+    fn parse_loops() {
+        let ExprKind::Apply(fun, inits) = expr("(let loop ((a 0) (b 1)) (loop b (+ a b)))") else {
+            panic!("expected application");
+        };
+        assert_eq!(inits.len(), 2);
+        let ExprKind::Letrec(name, lambda, body) = *fun.kind else {
+            panic!("expected letrec");
+        };
+        assert_eq!(*name.kind, PatternKind::Ident("loop".into()));
+        assert_eq!(*body.kind, ExprKind::Ident("loop".into()));
+        let ExprKind::Lambda(params, _) = *lambda.kind else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(&*params.kind, PatternKind::List(l) if l.iter().count() == 2));
+
+        let ExprKind::Letrec(name, lambda, body) =
+            expr("(letf (loop i . xs) (loop (- i 1)) (print 0) (loop 10))")
+        else {
+            panic!("expected letrec");
+        };
+        assert_eq!(*name.kind, PatternKind::Ident("loop".into()));
+        assert!(matches!(*lambda.kind, ExprKind::Lambda(..)));
+        assert!(matches!(*body.kind, ExprKind::Do(_)));
+    }
+
+    #[test]
+    fn parse_rich_patterns() {
+        let ExprKind::Match(_, arms) = expr(
+            "(match v
+               (_ 0)
+               ((x . xs) 1)
+               (#[a b rest...] 2)
+               ({:name n :age 30} 3)
+               (:ok 4)
+               ((as [x] one) 5)
+               ((or 1 2 3) 6)
+               ((or (:err e) #[e]) 7)
+               (n when (> n 0) (print n) 8))",
+        ) else {
+            panic!("expected match");
+        };
+        let kinds = arms.iter().map(|arm| &*arm.pat.kind).collect::<Vec<_>>();
+        assert_eq!(kinds[0], &PatternKind::Wildcard);
+        assert!(matches!(kinds[1], PatternKind::List(l) if l.dotted_tail().is_some()));
+        assert!(matches!(kinds[2], PatternKind::Vector(items, Some(_)) if items.len() == 2));
+        let PatternKind::Map(entries) = kinds[3] else {
+            panic!("expected map pattern");
+        };
+        assert_eq!(entries[0].0, Lit::Keyword("name".into()));
+        assert_eq!(*entries[0].1.kind, PatternKind::Ident("n".into()));
+        assert_eq!(kinds[4], &PatternKind::Lit(Lit::Keyword("ok".into())));
+        assert!(matches!(kinds[5], PatternKind::As(_, name) if &**name == "one"));
+        assert!(matches!(kinds[6], PatternKind::Or(alts) if alts.len() == 3));
+        assert!(matches!(kinds[7], PatternKind::Or(alts) if alts.len() == 2));
+        assert!(arms[..8].iter().all(|arm| arm.guard.is_none()));
+        assert!(matches!(
+            arms[8].guard.as_ref().map(|g| &*g.kind),
+            Some(ExprKind::Apply(..))
+        ));
+        assert!(matches!(&*arms[8].expr.kind, ExprKind::Do(exprs) if exprs.len() == 2));
+    }
+
+    #[test]
+    fn parse_quoted_patterns() {
+        let ExprKind::Match(_, arms) = expr("(match v ('a 1) ('(b c) 2))") else {
+            panic!("expected match");
+        };
+        assert!(matches!(&*arms[0].pat.kind, PatternKind::Quote(d) if d.to_string() == "a"));
+        assert!(matches!(&*arms[1].pat.kind, PatternKind::Quote(d) if d.to_string() == "(b c)"));
+        assert_eq!(
+            err("(match v ((quote a b) 1))"),
+            ("expected `(quote datum)`".to_string(), 10, 21)
+        );
+    }
+
+    #[test]
+    fn parse_bad_patterns() {
+        let expected = |msg: &str, start, end| (msg.to_string(), start, end);
+        assert_eq!(
+            err("(match v ((x x) 1))"),
+            expected("`x` is bound more than once in this pattern", 13, 14)
+        );
+        assert_eq!(
+            err("(fn (a {:k a}) a)"),
+            expected("`a` is bound more than once in this pattern", 11, 12)
+        );
+        assert_eq!(
+            err("(def (f x x) x)"),
+            expected("`x` is bound more than once in this pattern", 10, 11)
+        );
+        assert_eq!(
+            err("(let loop ((i 0) (i 1)) i)"),
+            expected("`i` is bound more than once in this pattern", 18, 19)
+        );
+        assert_eq!(
+            err("(match v ((as (x) x) x))"),
+            expected("`x` is bound more than once in this pattern", 10, 20)
+        );
+        assert_eq!(
+            err("(match v ((or (a) (b)) 1))"),
+            expected(
+                "every alternative of an or-pattern must bind the same names",
+                18,
+                21
+            )
+        );
+        assert_eq!(
+            err("(match v ({x 1} 1))"),
+            expected("map pattern keys must be literals", 11, 12)
+        );
+        assert_eq!(
+            err("(match v ((as x) 1))"),
+            expected("expected `(as pattern name)`", 10, 16)
+        );
+        assert_eq!(
+            err("(match v (x when (> x 0)))"),
+            expected("expected a `(pattern when guard body...)` match arm", 9, 25)
+        );
+        // `_` never binds, so it can repeat
+        assert!(matches!(expr("(fn (_ _) 1)"), ExprKind::Lambda(..)));
+    }
+
+    #[test]
+    fn parse_quasiquote() {
+        let ExprKind::Quasiquote(template) = expr("`(1 ,x ,@xs `(,,y))") else {
+            panic!("expected quasiquote");
+        };
+        let TemplateKind::List(elems, None) = *template.kind else {
+            panic!("expected list template");
+        };
+        assert!(matches!(*elems[0].kind, TemplateKind::Datum(_)));
+        assert!(matches!(*elems[1].kind, TemplateKind::Unquote(_)));
+        assert!(matches!(*elems[2].kind, TemplateKind::UnquoteSplicing(_)));
+        assert!(matches!(*elems[3].kind, TemplateKind::List(..)));
+        assert!(
+            matches!(expr("`(a b)"), ExprKind::Quasiquote(t) if matches!(*t.kind, TemplateKind::Datum(_)))
+        );
+    }
+
+    #[test]
+    fn parse_top_level() {
+        let (items, errs) = parse_src("(def x 10) x (add x 1)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(items[0], Item::Def(_)));
+        assert!(matches!(items[1], Item::Expr(_)));
+        assert!(matches!(items[2], Item::Expr(_)));
+    }
+
+    #[test]
+    fn parse_fn_defs() {
+        let (items, errs) = parse_src(
+            r#"(def (add x y) (+ x y))
+               (def (log fmt . args) "Prints a message." (print fmt args) nil)
+               (def (f) "s")
+               (def [a b] [1 2])"#,
+        );
+        assert!(errs.is_empty(), "{:?}", errs);
+        let defs = items
+            .into_iter()
+            .map(|item| match item {
+                Item::Def(def) => def,
+                Item::Expr(_) => panic!("expected a definition"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(*defs[0].pat.kind, PatternKind::Ident("add".into()));
+        let ExprKind::Lambda(params, _) = &*defs[0].expr.kind else {
+            panic!("expected lambda");
+        };
+        let PatternKind::List(params) = &*params.kind else {
+            panic!("expected parameter list");
+        };
+        assert_eq!(params.iter().count(), 2);
+        assert!(params.is_proper());
+        assert_eq!(defs[0].expr.span, defs[0].span);
+
+        let ExprKind::Lambda(params, body) = &*defs[1].expr.kind else {
+            panic!("expected lambda");
+        };
+        let PatternKind::List(params) = &*params.kind else {
+            panic!("expected parameter list");
+        };
+        assert!(params.dotted_tail().is_some());
+        assert!(matches!(&*body.kind, ExprKind::Do(exprs) if exprs.len() == 2));
+        assert_eq!(defs[1].doc, Some("Prints a message.".into()));
+
+        let ExprKind::Lambda(_, body) = &*defs[2].expr.kind else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(&*body.kind, ExprKind::Lit(Lit::String(_))));
+        assert_eq!(defs[2].doc, None);
+
+        assert!(matches!(&*defs[3].pat.kind, PatternKind::List(_)));
+        assert!(matches!(&*defs[3].expr.kind, ExprKind::List(_)));
+    }
+
+    #[test]
+    fn parse_multi_clause_defs() {
+        let (items, errs) = parse_src(
+            "(def (fib 0) 0)
+             (def (fib 1) \"Fibonacci numbers.\" 1)
+             (def (fib n) (+ (fib (- n 1)) (fib (- n 2))))
+             (fib 10)
+             (def (g x) x)
+             (def (g x) (+ x 1))",
+        );
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(items.len(), 4);
+        let Item::Def(fib) = &items[0] else {
+            panic!("expected a definition");
+        };
+        assert_eq!(*fib.pat.kind, PatternKind::Ident("fib".into()));
+        assert_eq!(fib.doc, Some("Fibonacci numbers.".into()));
+        let ExprKind::Lambda(args, body) = &*fib.expr.kind else {
+            panic!("expected lambda");
+        };
+        assert!(matches!(&*args.kind, PatternKind::Ident(_)));
+        let ExprKind::Match(_, arms) = &*body.kind else {
+            panic!("expected match");
+        };
+        assert_eq!(arms.len(), 3);
+        let PatternKind::List(params) = &*arms[0].pat.kind else {
+            panic!("expected parameter list");
+        };
+        assert!(matches!(
+            params.head().map(|p| &*p.kind),
+            Some(PatternKind::Lit(_))
+        ));
+        // all-name clauses are redefinitions
+        assert!(matches!(items[2], Item::Def(_)));
+        assert!(matches!(items[3], Item::Def(_)));
+    }
+
+    #[test]
+    fn parse_multi_clause_arity() {
+        assert_eq!(
+            err("(def (f 0) 0) (def (f x y) x)"),
+            (
+                "this clause of `f` takes 2 arguments, but the first clause takes 1 argument"
+                    .to_string(),
+                19,
+                26
+            )
+        );
+        assert_eq!(
+            err("(def (f 0 . xs) xs) (def (f x) x)"),
+            (
+                "this clause of `f` takes 1 argument, but the first clause takes at least 1 argument"
+                    .to_string(),
+                25,
+                30
+            )
+        );
+    }
+
+    #[test]
+    fn parse_malformed_forms() {
+        let expected = |msg: &str, start, end| (msg.to_string(), start, end);
+        assert_eq!(
+            err("(if 1 2)"),
+            expected("expected `(if cond then else)`", 0, 8)
+        );
+        assert_eq!(
+            err("(fn (x))"),
+            expected("expected `(fn params body...)`", 0, 8)
+        );
+        assert_eq!(
+            err("(let (x) x)"),
+            expected("expected a `(pattern expr)` binding", 6, 7)
+        );
+        assert_eq!(
+            err("(let 1 x)"),
+            expected("expected a list of `(pattern expr)` bindings", 5, 6)
+        );
+        assert_eq!(
+            err("(let loop ((i 0)))"),
+            expected("expected `(let name ((pattern expr)...) body...)`", 0, 18)
+        );
+        assert_eq!(
+            err("(letf (f x) x)"),
+            expected("expected `(letf (name params...) fbody body...)`", 0, 14)
+        );
+        assert_eq!(
+            err("(match x 1)"),
+            expected("expected a `(pattern body...)` match arm", 9, 10)
+        );
+        assert_eq!(
+            err("(f (quote a b))"),
+            expected("expected `(quote datum)`", 3, 14)
+        );
+        assert_eq!(
+            err("(f . x)"),
+            expected("dotted lists can't be evaluated", 5, 6)
+        );
+        assert_eq!(
+            err("()"),
+            expected(
+                "expected a function or special form; use `[]` for the empty list",
+                0,
+                2
+            )
+        );
+        assert_eq!(
+            err("(f (def x 1))"),
+            expected("`def` is only allowed at the top level", 3, 12)
+        );
+        assert_eq!(
+            err(",x"),
+            expected("`unquote` outside of a quasiquote", 0, 2)
+        );
+        assert_eq!(
+            err("`(a . ,@b)"),
+            expected("`unquote-splicing` must be an element of a list", 6, 9)
+        );
+        assert_eq!(
+            err("(def x)"),
+            expected(
+                "expected `(def pattern expr)` or `(def (name params...) body...)`",
+                0,
+                7
+            )
+        );
+        assert_eq!(
+            err("(def (1 x) x)"),
+            expected("expected a function name", 6, 7)
+        );
     }
 }
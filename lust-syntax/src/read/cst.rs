@@ -0,0 +1,483 @@
+/*
+ * Lossless concrete syntax tree for Lust source. Every byte of the input,
+ * including whitespace and comments, ends up in exactly one token of the
+ * tree, so the original text can always be recovered from it. The
+ * `sexpr::Root` handed to the rest of the front-end is derived from this
+ * tree by `read::lower_root`.
+ */
+use super::{token::Token, SyntaxError};
+use chumsky::prelude::Rich;
+use cstree::{
+    build::GreenNodeBuilder,
+    interning::TokenInterner,
+    syntax::{ResolvedElementRef, ResolvedNode, ResolvedToken},
+    text::TextRange,
+    Syntax,
+};
+use lust_utils::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Syntax)]
+#[repr(u32)]
+pub enum SyntaxKind {
+    // trivia
+    Whitespace,
+    Comment,
+    BlockComment,
+
+    // tokens
+    Ident,
+    Keyword,
+    Int,
+    Real,
+    Rational,
+    Bool,
+    Char,
+    String,
+    #[static_text("(")]
+    LParen,
+    #[static_text(")")]
+    RParen,
+    #[static_text("[")]
+    LBrack,
+    #[static_text("]")]
+    RBrack,
+    #[static_text("{")]
+    LBrace,
+    #[static_text("}")]
+    RBrace,
+    #[static_text(":")]
+    Colon,
+    #[static_text(".")]
+    Period,
+    #[static_text("...")]
+    Ellipsis,
+    #[static_text(",")]
+    Comma,
+    #[static_text(",@")]
+    CommaAt,
+    #[static_text("#")]
+    Hash,
+    #[static_text("#;")]
+    HashSemi,
+    #[static_text("#[")]
+    HashLBrack,
+    #[static_text("#{")]
+    HashLBrace,
+    #[static_text("#u8(")]
+    HashU8LParen,
+    #[static_text("'")]
+    Quote,
+    #[static_text("`")]
+    Backquote,
+    Error,
+
+    // nodes
+    Root,
+    Atom,
+    Path,
+    Variadic,
+    List,
+    DottedTail,
+    DataList,
+    Vector,
+    Map,
+    Set,
+    Bytes,
+    Prefixed,
+    Dispatch,
+    DatumComment,
+    ErrorNode,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace
+                | SyntaxKind::Comment
+                | SyntaxKind::BlockComment
+                | SyntaxKind::DatumComment
+        )
+    }
+
+    pub fn closer(self) -> Option<SyntaxKind> {
+        match self {
+            SyntaxKind::LParen | SyntaxKind::HashU8LParen => Some(SyntaxKind::RParen),
+            SyntaxKind::LBrack | SyntaxKind::HashLBrack => Some(SyntaxKind::RBrack),
+            SyntaxKind::LBrace | SyntaxKind::HashLBrace => Some(SyntaxKind::RBrace),
+            _ => None,
+        }
+    }
+
+    pub fn is_closer(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::RParen | SyntaxKind::RBrack | SyntaxKind::RBrace
+        )
+    }
+}
+
+impl From<&Token> for SyntaxKind {
+    fn from(token: &Token) -> Self {
+        match token {
+            Token::Eof | Token::Error => SyntaxKind::Error,
+            Token::Whitespace => SyntaxKind::Whitespace,
+            Token::Comment => SyntaxKind::Comment,
+            Token::BlockComment => SyntaxKind::BlockComment,
+            Token::Ident(_) => SyntaxKind::Ident,
+            Token::Keyword(_) => SyntaxKind::Keyword,
+            Token::Int(_) => SyntaxKind::Int,
+            Token::Real(_) => SyntaxKind::Real,
+            Token::Rational(_) => SyntaxKind::Rational,
+            Token::Bool(_) => SyntaxKind::Bool,
+            Token::Char(_) => SyntaxKind::Char,
+            Token::String(_) => SyntaxKind::String,
+            Token::LParen => SyntaxKind::LParen,
+            Token::RParen => SyntaxKind::RParen,
+            Token::LBrack => SyntaxKind::LBrack,
+            Token::RBrack => SyntaxKind::RBrack,
+            Token::LBrace => SyntaxKind::LBrace,
+            Token::RBrace => SyntaxKind::RBrace,
+            Token::Colon => SyntaxKind::Colon,
+            Token::Period => SyntaxKind::Period,
+            Token::Ellipsis => SyntaxKind::Ellipsis,
+            Token::Comma => SyntaxKind::Comma,
+            Token::CommaAt => SyntaxKind::CommaAt,
+            Token::Hash => SyntaxKind::Hash,
+            Token::HashSemi => SyntaxKind::HashSemi,
+            Token::HashLBrack => SyntaxKind::HashLBrack,
+            Token::HashLBrace => SyntaxKind::HashLBrace,
+            Token::HashU8LParen => SyntaxKind::HashU8LParen,
+            Token::Quote => SyntaxKind::Quote,
+            Token::Backquote => SyntaxKind::Backquote,
+        }
+    }
+}
+
+pub type SyntaxNode = ResolvedNode<SyntaxKind>;
+pub type SyntaxToken = ResolvedToken<SyntaxKind>;
+pub type SyntaxElementRef<'a> = ResolvedElementRef<'a, SyntaxKind>;
+
+pub fn span_of(range: TextRange) -> Span {
+    Span::new(range.start().into(), range.end().into())
+}
+
+/// The lexed values of a CST's tokens, keyed by where each token starts.
+/// Lowering looks values up here rather than lexing token text again.
+#[derive(Debug, Clone, Default)]
+pub struct TokenValues(Vec<(u32, Token)>);
+
+impl TokenValues {
+    // tokens must be pushed in source order
+    pub(super) fn push(&mut self, span: Span, token: Token) {
+        self.0.push((span.start(), token));
+    }
+
+    pub fn get(&self, token: &SyntaxToken) -> Option<&Token> {
+        let start = span_of(token.text_range()).start();
+        let i = self.0.binary_search_by_key(&start, |(s, _)| *s).ok()?;
+        Some(&self.0[i].1)
+    }
+}
+
+// Builds the green tree from the full token stream, trivia included.
+pub(super) struct CstBuilder<'src> {
+    tokens: Vec<(SyntaxKind, &'src str, Span)>,
+    pos: usize,
+    builder: GreenNodeBuilder<'static, 'static, SyntaxKind, TokenInterner>,
+    // closers of the sequences currently being built, innermost last
+    open: Vec<SyntaxKind>,
+    // whether the current top-level form fails to close, so a `(` in the
+    // first column may end it
+    resync: bool,
+    errs: Vec<SyntaxError<'src>>,
+}
+
+impl<'src> CstBuilder<'src> {
+    pub fn new(tokens: Vec<(SyntaxKind, &'src str, Span)>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            builder: GreenNodeBuilder::new(),
+            open: vec![],
+            resync: false,
+            errs: vec![],
+        }
+    }
+
+    pub fn build(mut self) -> (SyntaxNode, Vec<SyntaxError<'src>>) {
+        self.builder.start_node(SyntaxKind::Root);
+        loop {
+            self.skip_trivia();
+            match self.current() {
+                None => break,
+                Some(kind) if kind.is_closer() => self.unexpected(),
+                Some(_) => self.sexpr(),
+            }
+        }
+        self.builder.finish_node();
+        let (green, cache) = self.builder.finish();
+        let interner = cache.unwrap().into_interner().unwrap();
+        (
+            SyntaxNode::new_root_with_resolver(green, interner),
+            self.errs,
+        )
+    }
+
+    fn sexpr(&mut self) {
+        match self.current() {
+            Some(SyntaxKind::LParen) => self.seq(SyntaxKind::List),
+            Some(SyntaxKind::LBrack) => self.seq(SyntaxKind::DataList),
+            Some(SyntaxKind::HashLBrack) => self.seq(SyntaxKind::Vector),
+            Some(SyntaxKind::LBrace) => self.seq(SyntaxKind::Map),
+            Some(SyntaxKind::HashLBrace) => self.seq(SyntaxKind::Set),
+            Some(SyntaxKind::HashU8LParen) => self.seq(SyntaxKind::Bytes),
+            Some(
+                SyntaxKind::Quote | SyntaxKind::Backquote | SyntaxKind::Comma | SyntaxKind::CommaAt,
+            ) => self.prefixed(),
+            Some(SyntaxKind::Hash) if self.nth(1) == Some(SyntaxKind::Ident) => self.dispatch(),
+            Some(SyntaxKind::Ident) => self.ident(),
+            Some(
+                SyntaxKind::Keyword
+                | SyntaxKind::Int
+                | SyntaxKind::Real
+                | SyntaxKind::Rational
+                | SyntaxKind::Bool
+                | SyntaxKind::Char
+                | SyntaxKind::String,
+            ) => {
+                self.builder.start_node(SyntaxKind::Atom);
+                self.bump();
+                self.builder.finish_node();
+            }
+            Some(SyntaxKind::Error) => {
+                // already reported by the lexer
+                self.builder.start_node(SyntaxKind::ErrorNode);
+                self.bump();
+                self.builder.finish_node();
+            }
+            Some(_) => self.unexpected(),
+            None => self.error_at_end("expected expression"),
+        }
+    }
+
+    // "(" sexpr* ")" and friends; `kind` picks the node, the opener picks
+    // the closer. A missing closer is patched up with a virtual one, either
+    // at a closer that belongs to an enclosing sequence, at the start of the
+    // next top-level form or at the end of input.
+    fn seq(&mut self, kind: SyntaxKind) {
+        let (opener, open_span) = self.current_with_span().unwrap();
+        let closer = opener.closer().unwrap();
+        if self.open.is_empty() {
+            self.resync = !self.closes();
+        }
+        self.builder.start_node(kind);
+        self.bump();
+        self.open.push(closer);
+        let mut dotted = false;
+        loop {
+            self.skip_trivia();
+            match self.current() {
+                Some(kind) if kind == closer => {
+                    self.bump();
+                    break;
+                }
+                Some(SyntaxKind::Period) if kind == SyntaxKind::List => {
+                    self.dotted_tail(dotted);
+                    dotted = true;
+                }
+                Some(kind) if dotted && !kind.is_closer() && !self.at_top_level_form() => {
+                    self.error_at_current("expected `)` after the dotted tail");
+                    self.sexpr();
+                }
+                Some(kind) if kind.is_closer() && !self.open.contains(&kind) => self.unexpected(),
+                Some(kind) if !kind.is_closer() && !self.at_top_level_form() => self.sexpr(),
+                _ => {
+                    self.errs.push(SyntaxError::ParseError(Rich::custom(
+                        open_span,
+                        format!("unclosed `{}`", opener.static_text().unwrap()),
+                    )));
+                    break;
+                }
+            }
+        }
+        self.open.pop();
+        self.builder.finish_node();
+    }
+
+    // "." sexpr, ending an improper list
+    fn dotted_tail(&mut self, dotted: bool) {
+        if dotted {
+            self.error_at_current("a list can only have one dotted tail");
+        }
+        self.builder.start_node(SyntaxKind::DottedTail);
+        self.bump();
+        self.skip_trivia();
+        match self.current() {
+            Some(kind) if !kind.is_closer() && kind != SyntaxKind::Period => self.sexpr(),
+            _ => self.error_at_current("expected expression after `.`"),
+        }
+        self.builder.finish_node();
+    }
+
+    // "'" sexpr, "`" sexpr, "," sexpr, ",@" sexpr
+    fn prefixed(&mut self) {
+        self.builder.start_node(SyntaxKind::Prefixed);
+        self.bump();
+        self.skip_trivia();
+        match self.current() {
+            Some(kind) if !kind.is_closer() => self.sexpr(),
+            _ => self.error_at_current("expected expression"),
+        }
+        self.builder.finish_node();
+    }
+
+    // "#" symbol sexpr, expanded by the reader macro named `#symbol`
+    fn dispatch(&mut self) {
+        self.builder.start_node(SyntaxKind::Dispatch);
+        self.bump();
+        self.bump();
+        self.skip_trivia();
+        match self.current() {
+            Some(kind) if !kind.is_closer() => self.sexpr(),
+            _ => self.error_at_current("expected expression"),
+        }
+        self.builder.finish_node();
+    }
+
+    // path = symbol ("." symbol)+
+    // variadic = symbol "..."
+    fn ident(&mut self) {
+        match (self.nth(1), self.nth(2)) {
+            (Some(SyntaxKind::Period), Some(SyntaxKind::Ident)) => {
+                self.builder.start_node(SyntaxKind::Path);
+                self.bump();
+                while let (Some(SyntaxKind::Period), Some(SyntaxKind::Ident)) =
+                    (self.nth(0), self.nth(1))
+                {
+                    self.bump();
+                    self.bump();
+                }
+                self.builder.finish_node();
+            }
+            (Some(SyntaxKind::Ellipsis), _) => {
+                self.builder.start_node(SyntaxKind::Variadic);
+                self.bump();
+                self.bump();
+                self.builder.finish_node();
+            }
+            _ => {
+                self.builder.start_node(SyntaxKind::Atom);
+                self.bump();
+                self.builder.finish_node();
+            }
+        }
+    }
+
+    fn unexpected(&mut self) {
+        let (_, text, span) = self.tokens[self.pos];
+        self.errs.push(SyntaxError::ParseError(Rich::custom(
+            span,
+            format!("unexpected `{}`", text),
+        )));
+        self.builder.start_node(SyntaxKind::ErrorNode);
+        self.bump();
+        self.builder.finish_node();
+    }
+
+    fn error_at_current(&mut self, msg: &str) {
+        match self.current_with_span() {
+            Some((_, span)) => self
+                .errs
+                .push(SyntaxError::ParseError(Rich::custom(span, msg))),
+            None => self.error_at_end(msg),
+        }
+    }
+
+    fn error_at_end(&mut self, msg: &str) {
+        let end = self
+            .tokens
+            .last()
+            .map(|(_, _, span)| span.end())
+            .unwrap_or(0);
+        self.errs.push(SyntaxError::ParseError(Rich::custom(
+            Span::new(end, end),
+            msg,
+        )));
+    }
+
+    // A `(` in the first column almost always starts a new top-level form,
+    // so an unclosed form above it shouldn't swallow it. Forms that do
+    // close are left alone, whatever their layout.
+    fn at_top_level_form(&self) -> bool {
+        self.resync
+            && self.current() == Some(SyntaxKind::LParen)
+            && self.pos > 0
+            && self.tokens[self.pos - 1].1.ends_with('\n')
+    }
+
+    // Whether the sequence opened by the current token gets its closer,
+    // matching closers the way `seq` does: a closer of an enclosing
+    // sequence ends the inner ones early, a stray one is skipped.
+    fn closes(&self) -> bool {
+        let mut open = vec![];
+        for (kind, _, _) in &self.tokens[self.pos..] {
+            if let Some(closer) = kind.closer() {
+                open.push(closer);
+            } else if open.last() == Some(kind) {
+                open.pop();
+                if open.is_empty() {
+                    return true;
+                }
+            } else if open.contains(kind) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn current(&self) -> Option<SyntaxKind> {
+        self.nth(0)
+    }
+
+    fn current_with_span(&self) -> Option<(SyntaxKind, Span)> {
+        self.tokens
+            .get(self.pos)
+            .map(|(kind, _, span)| (*kind, *span))
+    }
+
+    // Looks ahead without skipping trivia, so `a.b` is a path but `a . b`
+    // is not.
+    fn nth(&self, n: usize) -> Option<SyntaxKind> {
+        self.tokens.get(self.pos + n).map(|(kind, _, _)| *kind)
+    }
+
+    fn bump(&mut self) {
+        let (kind, text, _) = self.tokens[self.pos];
+        self.builder.token(kind, text);
+        self.pos += 1;
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.current() {
+                Some(kind) if kind.is_trivia() => self.bump(),
+                Some(SyntaxKind::HashSemi) => self.datum_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    // "#;" sexpr, kept in the tree as trivia. Since the commented datum is
+    // read after skipping trivia itself, `#; #; a b` comments out both.
+    fn datum_comment(&mut self) {
+        self.builder.start_node(SyntaxKind::DatumComment);
+        self.bump();
+        self.skip_trivia();
+        match self.current() {
+            Some(kind) if !kind.is_closer() => self.sexpr(),
+            _ => self.error_at_current("expected expression after `#;`"),
+        }
+        self.builder.finish_node();
+    }
+}
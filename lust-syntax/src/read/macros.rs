@@ -0,0 +1,83 @@
+/*
+ * Reader macros rewrite the datum that follows (or, for `...`, precedes)
+ * their dispatch text while the CST is lowered to s-expressions. The
+ * built-in sugars are ordinary entries in the table:
+ *
+ *   'x       => (quote x)
+ *   `x       => (quasiquote x)
+ *   ,x       => (unquote x)
+ *   ,@x      => (unquote-splicing x)
+ *   x...     => (varg x)
+ *
+ * Any other `#name datum` form dispatches on `#name`, so new literal
+ * syntax like `#re"a+"` only needs an entry in the table.
+ */
+use super::sexpr::{Atom, AtomKind, Sexpr, SexprKind};
+use lust_utils::{intern::InternedString, list::List, span::Span};
+use std::{collections::HashMap, fmt::Debug, rc::Rc};
+
+/// Expands a reader macro. Gets the span of the dispatch text and the datum
+/// it applies to, and returns the form to read in their place or an error
+/// message to report at the whole form.
+pub type ReaderMacro = Rc<dyn Fn(Span, Sexpr) -> Result<SexprKind, String>>;
+
+#[derive(Clone)]
+pub struct ReaderMacros {
+    table: HashMap<String, ReaderMacro>,
+}
+
+impl ReaderMacros {
+    /// A table without any macros, not even the built-in ones.
+    pub fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+        }
+    }
+
+    /// Registers `expand` under `dispatch`, replacing any previous entry.
+    /// `dispatch` is `'`, `` ` ``, `,`, `,@`, `...` or `#` followed by a
+    /// symbol.
+    pub fn define(
+        &mut self,
+        dispatch: &str,
+        expand: impl Fn(Span, Sexpr) -> Result<SexprKind, String> + 'static,
+    ) {
+        self.table.insert(dispatch.to_string(), Rc::new(expand));
+    }
+
+    /// Registers a macro that reads `<dispatch>x` as `(name x)`.
+    pub fn define_wrapper(&mut self, dispatch: &str, name: &str) {
+        let name = InternedString::from(name);
+        self.define(dispatch, move |span, datum| {
+            let sym = Atom::new(AtomKind::Sym(name), span);
+            Ok(SexprKind::List(List::from([
+                Sexpr::new(SexprKind::Atom(sym), span),
+                datum,
+            ])))
+        });
+    }
+
+    pub fn get(&self, dispatch: &str) -> Option<&ReaderMacro> {
+        self.table.get(dispatch)
+    }
+}
+
+impl Default for ReaderMacros {
+    fn default() -> Self {
+        let mut macros = Self::new();
+        macros.define_wrapper("'", "quote");
+        macros.define_wrapper("`", "quasiquote");
+        macros.define_wrapper(",", "unquote");
+        macros.define_wrapper(",@", "unquote-splicing");
+        macros.define_wrapper("...", "varg");
+        macros
+    }
+}
+
+impl Debug for ReaderMacros {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut keys = self.table.keys().collect::<Vec<_>>();
+        keys.sort();
+        f.debug_set().entries(keys).finish()
+    }
+}
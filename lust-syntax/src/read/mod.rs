@@ -1,241 +1,832 @@
+pub mod cst;
+pub mod macros;
 pub mod sexpr;
+pub mod subst;
 pub mod token;
+pub mod visit;
+pub mod zipper;
 
 use self::{
+    cst::{span_of, CstBuilder, SyntaxKind, SyntaxNode, TokenValues},
+    macros::ReaderMacros,
     sexpr::{Atom, AtomKind, Lit, Root, Sexpr, SexprKind},
-    token::Token,
-};
-use chumsky::{
-    extra,
-    input::{Stream, ValueInput},
-    prelude::{Input, Rich},
-    primitive::just,
-    recursive::recursive,
-    select, IterParser, Parser,
+    token::{LexError, Token},
 };
+use chumsky::prelude::Rich;
+use cstree::Syntax;
 use logos::Logos;
-use lust_utils::{intern::InternedString, list::List, span::Span};
-use std::vec;
+use lust_utils::{
+    diagnostic::Diagnostic,
+    intern::InternedString,
+    list::List,
+    num::{Fraction, Integer},
+    span::Span,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SyntaxError<'a> {
     LexError(Span),
+    InvalidEscape(Span),
+    UnterminatedComment(Span),
     ParseError(Rich<'a, Token, Span, &'a str>),
 }
 
-pub fn read<'src>(src: &'src str) -> (Option<Root>, Vec<SyntaxError<'src>>) {
+impl From<SyntaxError<'_>> for Diagnostic {
+    fn from(err: SyntaxError<'_>) -> Self {
+        match err {
+            SyntaxError::LexError(span) => Diagnostic::error("invalid token")
+                .with_code("E0001")
+                .with_primary(span, "not part of any token"),
+            SyntaxError::InvalidEscape(span) => Diagnostic::error("invalid escape in string")
+                .with_code("E0002")
+                .with_primary(span, "unknown escape")
+                .with_help(r#"valid escapes are \n \t \r \0 \\ \" and \u{...}"#),
+            SyntaxError::UnterminatedComment(span) => {
+                Diagnostic::error("unterminated block comment")
+                    .with_code("E0003")
+                    .with_primary(span, "comment starts here")
+                    .with_help("close it with `|#`")
+            }
+            SyntaxError::ParseError(err) => Diagnostic::error(err.to_string())
+                .with_code("E0004")
+                .with_span(*err.span()),
+        }
+    }
+}
+
+/// Reads `src` into s-expressions. Malformed input never stops the reader:
+/// the returned `Root` holds every form that could be recovered, and the
+/// errors describe everything that was skipped or patched up.
+pub fn read<'src>(src: &'src str) -> (Root, Vec<SyntaxError<'src>>) {
+    read_with(src, &ReaderMacros::default())
+}
+
+/// Like `read`, but expands reader macros from `macros` instead of the
+/// built-in table.
+pub fn read_with<'src>(src: &'src str, macros: &ReaderMacros) -> (Root, Vec<SyntaxError<'src>>) {
+    let (cst, values, mut errs) = read_cst(src);
+    let root = lower_root(&cst, &values, macros, &mut errs);
+    (root, errs)
+}
+
+/// Whether a chunk of input is ready to be read, as decided by `input_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputStatus {
+    /// Every form is closed and reads without errors.
+    Complete,
+    /// The input stops inside a form, string or block comment; `closer` is
+    /// what would close the innermost one, e.g. `)` or `"`. Input that only
+    /// lacks the datum after a prefix like `'` or `#;` has an empty `closer`.
+    Incomplete { closer: &'static str },
+    /// The input has errors that more input can't fix.
+    Invalid,
+}
+
+/// Classifies `src` so hosts like the REPL know whether to read it now or
+/// keep prompting for continuation lines.
+pub fn input_status(src: &str) -> InputStatus {
+    let mut open = vec![];
+    // whether the last token that isn't trivia still waits for its datum
+    let mut prefixed = false;
+    let mut lexer = Token::lexer(src);
+    while let Some(res) = lexer.next() {
+        match res {
+            Ok(tok) => {
+                let kind = SyntaxKind::from(&tok);
+                if !kind.is_trivia() {
+                    prefixed = matches!(
+                        kind,
+                        SyntaxKind::Quote
+                            | SyntaxKind::Backquote
+                            | SyntaxKind::Comma
+                            | SyntaxKind::CommaAt
+                            | SyntaxKind::HashSemi
+                    );
+                }
+                if let Some(closer) = kind.closer() {
+                    open.push(closer);
+                } else if kind.is_closer() && open.pop() != Some(kind) {
+                    return InputStatus::Invalid;
+                }
+            }
+            Err(LexError::UnterminatedComment(_)) => {
+                return InputStatus::Incomplete { closer: "|#" }
+            }
+            // the string regex only fails to match when there's no closing quote
+            Err(LexError::InvalidToken) if lexer.slice().starts_with('"') => {
+                return InputStatus::Incomplete { closer: "\"" }
+            }
+            Err(_) => return InputStatus::Invalid,
+        }
+    }
+    match open.pop() {
+        Some(closer) => InputStatus::Incomplete {
+            closer: closer.static_text().unwrap(),
+        },
+        None if prefixed => InputStatus::Incomplete { closer: "" },
+        None if read(src).1.is_empty() => InputStatus::Complete,
+        None => InputStatus::Invalid,
+    }
+}
+
+/// Reads `src` into a lossless concrete syntax tree. Whitespace, comments
+/// and malformed input are all kept, so the tree's text is always `src`.
+/// The values the lexer gave the tree's tokens are returned alongside it.
+pub fn read_cst<'src>(src: &'src str) -> (SyntaxNode, TokenValues, Vec<SyntaxError<'src>>) {
     let mut errs = Vec::new();
     let mut tokens = vec![];
+    let mut values = TokenValues::default();
     for (res, span) in Token::lexer(src).spanned() {
-        match res {
-            Ok(tok) => tokens.push((tok, Span::from(span))),
-            Err(_) => {
-                errs.push(SyntaxError::LexError(Span::from(span.clone())));
-                tokens.push((Token::Error, Span::from(span)))
+        let kind = match res {
+            Ok(tok) => {
+                let kind = SyntaxKind::from(&tok);
+                if !kind.is_trivia() {
+                    values.push(Span::from(span.clone()), tok);
+                }
+                kind
             }
+            Err(err) => {
+                errs.push(match err {
+                    LexError::InvalidToken => SyntaxError::LexError(Span::from(span.clone())),
+                    LexError::InvalidEscape(esc) => SyntaxError::InvalidEscape(esc),
+                    LexError::UnterminatedComment(open) => SyntaxError::UnterminatedComment(open),
+                });
+                SyntaxKind::Error
+            }
+        };
+        tokens.push((kind, &src[span.clone()], Span::from(span)));
+    }
+    let (cst, parse_errs) = CstBuilder::new(tokens).build();
+    errs.extend(parse_errs);
+    (cst, values, errs)
+}
+
+/// Derives the s-expression tree from a concrete syntax tree, dropping
+/// trivia and expanding reader macros like `'x`.
+pub fn lower_root<'src>(
+    cst: &SyntaxNode,
+    values: &TokenValues,
+    macros: &ReaderMacros,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Root {
+    let cx = Lowering { values, macros };
+    Root::new(lower_children(cst, &cx, errs), span_of(cst.text_range()))
+}
+
+// What lowering reads besides the tree itself.
+struct Lowering<'a> {
+    values: &'a TokenValues,
+    macros: &'a ReaderMacros,
+}
+
+// The datum a prefix or dispatch applies to, skipping any `#;` comments
+// between them.
+fn operand(node: &SyntaxNode) -> Option<&SyntaxNode> {
+    node.children().find(|child| !child.kind().is_trivia())
+}
+
+fn lower_sexpr<'src>(
+    node: &SyntaxNode,
+    cx: &Lowering,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Option<Sexpr> {
+    let span = span_of(node.text_range());
+    let kind = match node.kind() {
+        SyntaxKind::Atom => SexprKind::Atom(Atom::new(
+            lower_atom(cx.values.get(node.first_token()?)?)?,
+            span,
+        )),
+        // path = symbol ("." symbol)+
+        SyntaxKind::Path => {
+            let path = node
+                .children_with_tokens()
+                .filter_map(|elem| elem.into_token())
+                .filter(|tok| tok.kind() == SyntaxKind::Ident)
+                .map(|tok| InternedString::from(tok.text()))
+                .collect();
+            SexprKind::Atom(Atom::new(AtomKind::Path(path), span))
+        }
+        // foo... dispatches on the trailing `...`
+        SyntaxKind::Variadic => {
+            let name = node.first_token()?;
+            let suffix = node.last_token()?;
+            let datum = sym_sexpr(name.text(), span_of(name.text_range()));
+            let suffix_span = span_of(suffix.text_range());
+            expand_macro(suffix.text(), suffix_span, datum, span, cx.macros, errs)?
+        }
+        SyntaxKind::List => SexprKind::List(list_elems(node, cx, errs)),
+        SyntaxKind::DataList => SexprKind::DataList(lower_children(node, cx, errs)),
+        SyntaxKind::Vector => SexprKind::Vector(lower_children(node, cx, errs)),
+        SyntaxKind::Map => SexprKind::Map(map_entries(lower_children(node, cx, errs), span, errs)),
+        SyntaxKind::Set => SexprKind::Set(set_elems(lower_children(node, cx, errs), errs)),
+        SyntaxKind::Bytes => SexprKind::Atom(Atom::new(
+            AtomKind::Lit(Lit::Bytes(byte_elems(node, cx.values, errs))),
+            span,
+        )),
+        SyntaxKind::Prefixed => {
+            let prefix = node.first_token()?;
+            let prefix_span = span_of(prefix.text_range());
+            let datum = lower_sexpr(operand(node)?, cx, errs)?;
+            expand_macro(prefix.text(), prefix_span, datum, span, cx.macros, errs)?
         }
+        // "#" symbol sexpr dispatches on `#symbol`
+        SyntaxKind::Dispatch => {
+            let name = node.first_token()?.next_token()?;
+            let dispatch = format!("#{}", name.text());
+            let dispatch_span = Span::new(span.start(), span_of(name.text_range()).end());
+            let datum = lower_sexpr(operand(node)?, cx, errs)?;
+            expand_macro(&dispatch, dispatch_span, datum, span, cx.macros, errs)?
+        }
+        _ => return None,
+    };
+    Some(Sexpr::new(kind, span))
+}
+
+fn lower_children<'src>(
+    node: &SyntaxNode,
+    cx: &Lowering,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Vec<Sexpr> {
+    node.children()
+        .filter_map(|child| lower_sexpr(child, cx, errs))
+        .collect()
+}
+
+// list = "(" sexpr* ("." sexpr)? ")"
+fn list_elems<'src>(
+    node: &SyntaxNode,
+    cx: &Lowering,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> List<Sexpr> {
+    let mut items = vec![];
+    for child in node.children() {
+        if child.kind() != SyntaxKind::DottedTail {
+            items.extend(lower_sexpr(child, cx, errs));
+            continue;
+        }
+        let Some(tail) = lower_children(child, cx, errs).pop() else {
+            continue;
+        };
+        if items.is_empty() {
+            errs.push(SyntaxError::ParseError(Rich::custom(
+                span_of(child.text_range()),
+                "expected an expression before `.`",
+            )));
+            items.push(tail);
+            continue;
+        }
+        // anything after the tail has already been reported by the builder
+        return List::improper(items, tail);
     }
-    if !errs.is_empty() {
-        return (None, errs);
+    List::from(items)
+}
+
+// Expands the reader macro registered under `dispatch`, reporting unknown
+// macros at the dispatch text and expansion errors at the whole form.
+fn expand_macro<'src>(
+    dispatch: &str,
+    dispatch_span: Span,
+    datum: Sexpr,
+    span: Span,
+    macros: &ReaderMacros,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Option<SexprKind> {
+    let Some(expand) = macros.get(dispatch) else {
+        errs.push(SyntaxError::ParseError(Rich::custom(
+            dispatch_span,
+            format!("unknown reader macro `{}`", dispatch),
+        )));
+        return None;
+    };
+    match expand(dispatch_span, datum) {
+        Ok(kind) => Some(kind),
+        Err(msg) => {
+            errs.push(SyntaxError::ParseError(Rich::custom(span, msg)));
+            None
+        }
     }
-    println!("tokens: {:?}", tokens);
-    let tok_stream = Stream::from_iter(tokens).spanned(Span::from(src.len()..src.len()));
-    let (root, errs) = root_reader().parse(tok_stream).into_output_errors();
-    (
-        root,
-        errs.into_iter()
-            .map(|err| SyntaxError::ParseError(err))
-            .collect(),
-    )
 }
 
-fn root_reader<'a, I: ValueInput<'a, Token = Token, Span = Span>>(
-) -> impl Parser<'a, I, Root, extra::Err<Rich<'a, Token, Span>>> {
-    sexpr_reader()
-        .repeated()
-        .collect()
-        .map_with_span(Root::new)
-        .boxed()
+fn lower_atom(token: &Token) -> Option<AtomKind> {
+    Some(match token.clone() {
+        Token::Ident(name) => AtomKind::Sym(name),
+        Token::Keyword(name) => AtomKind::Keyword(name),
+        Token::Int(Integer::Small(n)) => AtomKind::Lit(Lit::Int(n)),
+        Token::Int(Integer::Big(n)) => AtomKind::Lit(Lit::BigInt(n)),
+        Token::Real(n) => AtomKind::Lit(Lit::Real(n)),
+        Token::Rational(Fraction::Small(n)) => AtomKind::Lit(Lit::Rational(n)),
+        Token::Rational(Fraction::Big(n)) => AtomKind::Lit(Lit::BigRational(n)),
+        Token::Bool(b) => AtomKind::Lit(Lit::Bool(b)),
+        Token::Char(c) => AtomKind::Lit(Lit::Char(c)),
+        Token::String(s) => AtomKind::Lit(Lit::String(s)),
+        _ => return None,
+    })
 }
 
-fn sexpr_reader<'a, I: ValueInput<'a, Token = Token, Span = Span>>(
-) -> impl Parser<'a, I, Sexpr, extra::Err<Rich<'a, Token, Span>>> {
-    recursive(|sexpr| {
-        // path = symbol ("." symbol)+
-        let path = ident_reader()
-            .then(
-                just(Token::Period)
-                    .ignore_then(ident_reader())
-                    .repeated()
-                    .at_least(1)
-                    .collect::<Vec<_>>(),
-            )
-            .map(|(lhs, rhs)| {
-                let mut v = vec![lhs];
-                v.extend(rhs);
-                v
-            })
-            .map(AtomKind::Path);
-
-        let atom = path
-            .or(ident_reader().map(AtomKind::Sym))
-            .or(lit_reader().map(AtomKind::Lit))
-            .map_with_span(Atom::new)
-            .map(SexprKind::Atom)
-            .map_with_span(Sexpr::new)
-            .boxed();
-
-        let list = sexpr
-            .clone()
-            .repeated()
-            .at_least(1)
-            .collect::<Vec<_>>()
-            .map(List::from)
-            .map(SexprKind::List)
-            .map_with_span(Sexpr::new)
-            .delimited_by(just(Token::LParen), just(Token::RParen));
-
-        let list_lit = sexpr
-            .clone()
-            .repeated()
-            .at_least(1)
-            .collect::<Vec<_>>()
-            .map(List::from)
-            .map_with_span(|mut list, span: Span| {
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(
-                        AtomKind::Sym(InternedString::from("list")),
-                        Span::from(span.start()..span.start()),
-                    )),
-                    span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new)
-            .delimited_by(just(Token::LBrack), just(Token::RBrack));
-
-        let vector = sexpr
-            .clone()
-            .repeated()
-            .collect::<Vec<_>>()
-            .map(List::from)
-            .map(SexprKind::List)
-            .map_with_span(Sexpr::new)
-            .delimited_by(just(Token::HashLBrack), just(Token::RBrack));
-
-        // quote = "'" sexpr
-        let quote = just(Token::Quote)
-            .map_with_span(|_, span| span)
-            .then(sexpr.clone())
-            .map(|(span, sexpr)| {
-                let mut list = List::Empty;
-                list.push_front(sexpr);
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(
-                        AtomKind::Sym(InternedString::from("quote")),
-                        span,
-                    )),
-                    span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new);
-
-        let quasiquote = just(Token::Backquote)
-            .map_with_span(|_, span| span)
-            .then(sexpr.clone())
-            .map(|(span, sexpr)| {
-                let mut list = List::Empty;
-                list.push_front(sexpr);
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(
-                        AtomKind::Sym(InternedString::from("quasiquote")),
-                        span,
-                    )),
-                    span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new);
-
-        let unquote = just(Token::Comma)
-            .map_with_span(|_, span| span)
-            .then(sexpr.clone())
-            .map(|(span, sexpr)| {
-                let mut list = List::Empty;
-                list.push_front(sexpr);
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(
-                        AtomKind::Sym(InternedString::from("unquote")),
-                        span,
-                    )),
-                    span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new);
-
-        let unquote_splice = just(Token::CommaAt)
-            .map_with_span(|_, span| span)
-            .then(sexpr.clone())
-            .map(|(span, sexpr)| {
-                let mut list = List::Empty;
-                list.push_front(sexpr);
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(
-                        AtomKind::Sym(InternedString::from("unquote-splicing")),
-                        span,
-                    )),
-                    span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new);
-
-        // map foo... to (vargs foo)
-        let variadic = ident_reader()
-            .then_ignore(just(Token::Ellipsis))
-            .map_with_span(|name, span| {
-                let mut list = List::Empty;
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(AtomKind::Sym(name), span)),
+fn sym_sexpr(name: &str, span: Span) -> Sexpr {
+    let sym = Atom::new(AtomKind::Sym(InternedString::from(name)), span);
+    Sexpr::new(SexprKind::Atom(sym), span)
+}
+
+// Pairs up the forms of a map literal, reporting a dangling key and any
+// literal key that appears more than once.
+fn map_entries<'src>(
+    items: Vec<Sexpr>,
+    span: Span,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Vec<(Sexpr, Sexpr)> {
+    if !items.len().is_multiple_of(2) {
+        errs.push(SyntaxError::ParseError(Rich::custom(
+            span,
+            "map literal must have an even number of forms",
+        )));
+    }
+    let mut entries: Vec<(Sexpr, Sexpr)> = Vec::with_capacity(items.len() / 2);
+    let mut iter = items.into_iter();
+    while let (Some(key), Some(val)) = (iter.next(), iter.next()) {
+        if let Some(lit) = literal_key(&key) {
+            if entries.iter().any(|(k, _)| literal_key(k) == Some(lit)) {
+                errs.push(SyntaxError::ParseError(Rich::custom(
+                    key.span,
+                    format!("duplicate key `{}` in map literal", key),
+                )));
+            }
+        }
+        entries.push((key, val));
+    }
+    entries
+}
+
+// Reports any literal element that appears more than once in a set literal.
+fn set_elems<'src>(items: Vec<Sexpr>, errs: &mut Vec<SyntaxError<'src>>) -> Vec<Sexpr> {
+    for (i, item) in items.iter().enumerate() {
+        if let Some(lit) = literal_key(item) {
+            if items[..i].iter().any(|s| literal_key(s) == Some(lit)) {
+                errs.push(SyntaxError::ParseError(Rich::custom(
+                    item.span,
+                    format!("duplicate element `{}` in set literal", item),
+                )));
+            }
+        }
+    }
+    items
+}
+
+// Narrows the elements of a byte-array literal to `u8`, reporting any that
+// aren't integers in 0..=255.
+fn byte_elems<'src>(
+    node: &SyntaxNode,
+    values: &TokenValues,
+    errs: &mut Vec<SyntaxError<'src>>,
+) -> Vec<u8> {
+    node.children()
+        .filter(|child| !child.kind().is_trivia() && child.kind() != SyntaxKind::ErrorNode)
+        .filter_map(|child| {
+            let span = span_of(child.text_range());
+            let value = match child.kind() {
+                SyntaxKind::Atom => child.first_token().and_then(|tok| values.get(tok)).cloned(),
+                _ => None,
+            };
+            let byte = match &value {
+                Some(Token::Int(Integer::Small(i))) => u8::try_from(i.value()).ok(),
+                _ => None,
+            };
+            match (byte, value) {
+                (Some(byte), _) => return Some(byte),
+                (None, Some(Token::Int(n))) => errs.push(SyntaxError::ParseError(Rich::custom(
                     span,
-                ));
-                list.push_front(Sexpr::new(
-                    SexprKind::Atom(Atom::new(AtomKind::Sym(InternedString::from("varg")), span)),
+                    format!("byte literal `{}` out of range 0..=255", n),
+                ))),
+                (None, _) => errs.push(SyntaxError::ParseError(Rich::custom(
                     span,
-                ));
-                SexprKind::List(list)
-            })
-            .map_with_span(Sexpr::new)
-            .boxed();
-
-        variadic
-            .or(list)
-            .or(list_lit)
-            .or(vector)
-            .or(quote)
-            .or(quasiquote)
-            .or(unquote)
-            .or(unquote_splice)
-            .or(atom)
-    })
+                    "expected a byte literal",
+                ))),
+            }
+            None
+        })
+        .collect()
 }
 
-fn ident_reader<'a, I: ValueInput<'a, Token = Token, Span = Span>>(
-) -> impl Parser<'a, I, InternedString, extra::Err<Rich<'a, Token, Span>>> {
-    select! {
-        Token::Ident(name) => name,
+fn literal_key(sexpr: &Sexpr) -> Option<&AtomKind> {
+    match &*sexpr.kind {
+        SexprKind::Atom(a) => match &*a.kind {
+            kind @ (AtomKind::Lit(_) | AtomKind::Keyword(_)) => Some(kind),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
-fn lit_reader<'a, I: ValueInput<'a, Token = Token, Span = Span>>(
-) -> impl Parser<'a, I, Lit, extra::Err<Rich<'a, Token, Span>>> {
-    select! {
-        Token::Int(n) => Lit::Int(n),
-        Token::Real(n) => Lit::Real(n),
-        Token::Rational(n) => Lit::Rational(n),
-        Token::Bool(b) => Lit::Bool(b),
-        Token::String(s) => Lit::String(s),
+#[cfg(test)]
+mod tests {
+    use super::{
+        input_status,
+        macros::ReaderMacros,
+        read, read_cst, read_with,
+        sexpr::{AtomKind, Lit, SexprKind},
+        token::Token,
+        InputStatus, SyntaxError,
+    };
+    use lust_utils::{intern::InternedString, num::Integer, span::Span};
+
+    #[test]
+    fn read_map() {
+        let (root, errs) = read("{:start 1 :end 2}");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(*root.sexprs[0].kind, SexprKind::Map(ref m) if m.len() == 2));
+    }
+
+    #[test]
+    fn read_set() {
+        let (root, errs) = read("#{1 2 3}");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(*root.sexprs[0].kind, SexprKind::Set(ref s) if s.len() == 3));
+        assert_eq!(root.to_string(), "#{1 2 3}\n");
+    }
+
+    #[test]
+    fn read_set_duplicate_elem() {
+        let (_, errs) = read("#{1 2 1}");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn read_bytes() {
+        let (root, errs) = read("#u8(1 2 255)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(
+            root.sexprs[0].as_atom().and_then(|a| a.as_lit()),
+            Some(Lit::Bytes(ref b)) if b == &[1, 2, 255]
+        ));
+        assert_eq!(root.to_string(), "#u8(1 2 255)\n");
+    }
+
+    #[test]
+    fn read_bytes_out_of_range() {
+        let (_, errs) = read("#u8(1 256)");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn read_char() {
+        let (root, errs) = read(r"#\a #\space #\newline #\x41 #\u{1F600} #\(");
+        assert!(errs.is_empty(), "{:?}", errs);
+        let chars = root
+            .sexprs
+            .iter()
+            .map(|s| s.as_atom().and_then(|a| a.as_lit()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chars,
+            vec![
+                Some(Lit::Char('a')),
+                Some(Lit::Char(' ')),
+                Some(Lit::Char('\n')),
+                Some(Lit::Char('A')),
+                Some(Lit::Char('😀')),
+                Some(Lit::Char('(')),
+            ]
+        );
+        assert_eq!(Lit::Char(' ').to_string(), r"#\space");
+        assert_eq!(Lit::Char('\u{1}').to_string(), r"#\x1");
+        assert_eq!(Lit::Char('λ').to_string(), r"#\λ");
+    }
+
+    #[test]
+    fn read_string() {
+        let (root, errs) = read(
+            r#""a\"b\\c\n\t\u{41}\
+              d""#,
+        );
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(
+            root.sexprs[0].as_atom().and_then(|a| a.as_lit()),
+            Some(Lit::String(InternedString::from("a\"b\\c\n\tAd")))
+        );
+        assert_eq!(root.to_string(), "\"a\\\"b\\\\c\\n\\tAd\"\n");
+    }
+
+    #[test]
+    fn read_string_invalid_escape() {
+        let (_, errs) = read(r#""ab\qc""#);
+        assert_eq!(
+            errs,
+            vec![SyntaxError::InvalidEscape(Span::from(3..5usize))]
+        );
+        let (_, errs) = read(r#""\u{110000}""#);
+        assert_eq!(
+            errs,
+            vec![SyntaxError::InvalidEscape(Span::from(1..11usize))]
+        );
+    }
+
+    #[test]
+    fn read_big_numbers() {
+        let (root, errs) = read("123456789012345678901234567890 1/123456789012345678901234567890");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(
+            root.sexprs[0].as_atom().and_then(|a| a.as_lit()),
+            Some(Lit::BigInt(_))
+        ));
+        assert!(matches!(
+            root.sexprs[1].as_atom().and_then(|a| a.as_lit()),
+            Some(Lit::BigRational(_))
+        ));
+        assert_eq!(
+            root.to_string(),
+            "123456789012345678901234567890\n1/123456789012345678901234567890\n"
+        );
+    }
+
+    #[test]
+    fn read_numbers() {
+        let (root, errs) = read("-5 +0xff 1_000 1e3 -2.5 +inf.0 -0b10/3 - -x");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(
+            root.to_string(),
+            "-5\n255\n1000\n1000.0\n-2.5\n+inf.0\n-2/3\n-\n-x\n"
+        );
+    }
+
+    #[test]
+    fn read_keyword() {
+        let (root, errs) = read(":foo");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(
+            root.sexprs[0].as_atom().map(|a| *a.kind),
+            Some(AtomKind::Keyword(k)) if &*k == "foo"
+        ));
+        assert_eq!(root.to_string(), ":foo\n");
+    }
+
+    #[test]
+    fn read_map_odd_forms() {
+        let (_, errs) = read("{1 2 3}");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn read_map_duplicate_key() {
+        let (_, errs) = read("{1 2 1 3}");
+        assert_eq!(errs.len(), 1);
+        let (_, errs) = read("{:a 1 :a 2}");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn read_cst_lossless() {
+        let src = "; header\n(def x 1) ; trailing\n'[a b] {:k v}\n  ";
+        let (cst, _, errs) = read_cst(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(cst.text().to_string(), src);
+    }
+
+    #[test]
+    fn read_cst_token_values() {
+        let (cst, values, errs) = read_cst(r#"(f 0x10 "a\n")"#);
+        assert!(errs.is_empty(), "{:?}", errs);
+        let tokens = cst
+            .descendants_with_tokens()
+            .filter_map(|elem| elem.into_token())
+            .filter(|tok| !tok.kind().is_trivia())
+            .map(|tok| values.get(tok).cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Some(Token::LParen),
+                Some(Token::Ident("f".into())),
+                Some(Token::Int(Integer::Small("16".parse().unwrap()))),
+                Some(Token::String("a\n".into())),
+                Some(Token::RParen),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_cst_keeps_bad_input() {
+        let src = "(a ) ) (b";
+        let (cst, _, errs) = read_cst(src);
+        assert_eq!(errs.len(), 2);
+        assert_eq!(cst.text().to_string(), src);
+    }
+
+    #[test]
+    fn read_with_comments() {
+        let (root, errs) = read("; doc\n(f ; arg\n x)\n; end");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(root.to_string(), "(f x)\n");
+        assert_eq!(root.sexprs[0].span, Span::new(6, 18));
+    }
+
+    #[test]
+    fn read_recovers_missing_closer() {
+        let (root, errs) = read("(def f (g x)\n(def y 1)");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(root.to_string(), "(def f (g x))\n(def y 1)\n");
+    }
+
+    #[test]
+    fn read_keeps_closed_forms_in_the_first_column() {
+        let (root, errs) = read("(def (f x)\n(g x))\n(let ((x 1))\n(print x))");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(
+            root.to_string(),
+            "(def (f x) (g x))\n(let ((x 1)) (print x))\n"
+        );
+    }
+
+    #[test]
+    fn read_recovers_mismatched_closer() {
+        let (root, errs) = read("[(a b] (c)");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(root.to_string(), "[(a b)]\n(c)\n");
+    }
+
+    #[test]
+    fn read_skips_stray_closer() {
+        let (root, errs) = read("(a)) (b ] c)");
+        assert_eq!(errs.len(), 2);
+        assert_eq!(root.to_string(), "(a)\n(b c)\n");
+    }
+
+    #[test]
+    fn read_recovers_from_lex_errors() {
+        let (root, errs) = read("(a \"\\q\" b) (c)");
+        assert_eq!(errs.len(), 1);
+        assert_eq!(root.to_string(), "(a b)\n(c)\n");
+    }
+
+    #[test]
+    fn read_block_comment() {
+        let src = "(a #| one #| two |# still one |# b)";
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(root.to_string(), "(a b)\n");
+        let (cst, _, _) = read_cst(src);
+        assert_eq!(cst.text().to_string(), src);
+    }
+
+    #[test]
+    fn read_unterminated_block_comment() {
+        let (root, errs) = read("a #| b #| c |#");
+        assert_eq!(
+            errs,
+            vec![SyntaxError::UnterminatedComment(Span::new(2, 4))]
+        );
+        assert_eq!(root.to_string(), "a\n");
+    }
+
+    #[test]
+    fn read_datum_comment() {
+        let (root, errs) = read("#;(a b) c (d #; #; e f g) #u8(1 #;2 3)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(root.to_string(), "c\n(d g)\n#u8(1 3)\n");
+        let (_, errs) = read("(a #;)");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn read_datum_comment_after_prefix() {
+        let (root, errs) = read("'#;a b `#;x y");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(root.to_string(), "(quote b)\n(quasiquote y)\n");
+    }
+
+    #[test]
+    fn read_datum_comment_before_operand() {
+        let mut macros = ReaderMacros::default();
+        macros.define_wrapper("#re", "regex");
+        let (root, errs) = read_with(r#"#re #;"a" "b+" (,#;x y ,@ #; #; z w v)"#, &macros);
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(
+            root.to_string(),
+            "(regex \"b+\")\n((unquote y) (unquote-splicing v))\n"
+        );
+    }
+
+    #[test]
+    fn read_builtin_reader_macros() {
+        let (root, errs) = read("'a `(b ,c ,@d) (f xs...)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(
+            root.to_string(),
+            "(quote a)\n(quasiquote (b (unquote c) (unquote-splicing d)))\n(f (varg xs))\n"
+        );
+    }
+
+    #[test]
+    fn read_user_reader_macros() {
+        let mut macros = ReaderMacros::default();
+        macros.define_wrapper("#re", "regex");
+        macros.define("#date", |_, datum| {
+            match datum.as_atom().and_then(|a| a.as_lit()) {
+                Some(Lit::String(s)) if s.len() == 10 => {
+                    Ok(SexprKind::Atom(datum.as_atom().unwrap().clone()))
+                }
+                _ => Err("expected a date string like \"2024-01-31\"".to_string()),
+            }
+        });
+        let (root, errs) = read_with(r#"(m #re"a+" #date "2024-01-31")"#, &macros);
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert_eq!(root.to_string(), "(m (regex \"a+\") \"2024-01-31\")\n");
+
+        let (_, errs) = read_with("#date 12", &macros);
+        assert_eq!(errs.len(), 1);
+        let (_, errs) = read("#re\"a+\"");
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn classify_input() {
+        assert_eq!(input_status("(def x 1) 'y"), InputStatus::Complete);
+        assert_eq!(input_status(""), InputStatus::Complete);
+        assert_eq!(
+            input_status("(def (f x)\n  [x"),
+            InputStatus::Incomplete { closer: "]" }
+        );
+        assert_eq!(
+            input_status("#u8(1 2"),
+            InputStatus::Incomplete { closer: ")" }
+        );
+        assert_eq!(
+            input_status("(f \"abc\n"),
+            InputStatus::Incomplete { closer: "\"" }
+        );
+        assert_eq!(
+            input_status("#| (a"),
+            InputStatus::Incomplete { closer: "|#" }
+        );
+        assert_eq!(input_status("(a]"), InputStatus::Invalid);
+        assert_eq!(input_status("a)"), InputStatus::Invalid);
+        assert_eq!(input_status("{1 2 3}"), InputStatus::Invalid);
+    }
+
+    #[test]
+    fn classify_trailing_prefix() {
+        for src in ["'", "(def x 1) `", ",", ",@ ; more to come\n", "a #;"] {
+            assert_eq!(
+                input_status(src),
+                InputStatus::Incomplete { closer: "" },
+                "{:?}",
+                src
+            );
+        }
+        assert_eq!(
+            input_status("(f '"),
+            InputStatus::Incomplete { closer: ")" }
+        );
+        assert_eq!(input_status("'#;a b"), InputStatus::Complete);
+    }
+
+    #[test]
+    fn read_vectors_and_data_lists() {
+        let src = "[1 [2] #[3 (4)]] #[] []";
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        assert!(matches!(&*root.sexprs[0].kind, SexprKind::DataList(v) if v.len() == 3));
+        assert!(matches!(&*root.sexprs[1].kind, SexprKind::Vector(v) if v.is_empty()));
+        assert_eq!(root.to_string(), "[1 [2] #[3 (4)]]\n#[]\n[]\n");
+    }
+
+    #[test]
+    fn read_paths() {
+        let (root, errs) = read("(Map.get m :a) (m.a.b)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        let head = root.sexprs[0].as_list().unwrap().head().unwrap().clone();
+        assert!(matches!(head.as_atom().unwrap().kind(), AtomKind::Path(p) if p.len() == 2));
+        assert_eq!(root.to_string(), "(Map.get m :a)\n(m.a.b)\n");
+    }
+
+    #[test]
+    fn read_dotted_pairs() {
+        let (root, errs) = read("(a . b) (matches? obj . cases) (x xs...) (a.b . c)");
+        assert!(errs.is_empty(), "{:?}", errs);
+        let SexprKind::List(list) = &*root.sexprs[1].kind else {
+            panic!("expected list");
+        };
+        assert_eq!(list.iter().count(), 2);
+        assert_eq!(list.dotted_tail().unwrap().to_string(), "cases");
+        assert_eq!(
+            root.to_string(),
+            "(a . b)\n(matches? obj . cases)\n(x (varg xs))\n(a.b . c)\n"
+        );
+    }
+
+    #[test]
+    fn read_bad_dotted_pairs() {
+        for src in [
+            "(. a)",
+            "(a . b c)",
+            "(a .)",
+            "(a . b . c)",
+            "[a . b]",
+            ". a",
+        ] {
+            let (_, errs) = read(src);
+            assert!(!errs.is_empty(), "{}", src);
+        }
+        let (root, _) = read("(a . b c)");
+        assert_eq!(root.to_string(), "(a . b)\n");
     }
 }
@@ -1,3 +1,4 @@
+use super::token::CHAR_NAMES;
 use lust_utils::{
     intern::InternedString,
     list::List,
@@ -12,6 +13,12 @@ pub struct Root {
     pub span: Span,
 }
 
+impl Root {
+    pub fn new(sexprs: Vec<Sexpr>, span: Span) -> Self {
+        Self { sexprs, span }
+    }
+}
+
 impl Display for Root {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for s in &self.sexprs {
@@ -35,14 +42,24 @@ impl Sexpr {
         }
     }
 
+    pub fn kind(&self) -> &SexprKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
     pub fn as_special_form(&self) -> Option<&str> {
-        match *self.kind {
+        match &*self.kind {
             SexprKind::List(l) => match l.head() {
-                Some(head) => match *head.kind {
-                    SexprKind::Atom(a) => match *a.kind {
+                Some(head) => match &*head.kind {
+                    SexprKind::Atom(a) => match &*a.kind {
                         AtomKind::Sym(s) => match s.as_ref() {
-                            "def" | "let" | "quote" | "fn" | "and" | "or" | "match"
-                            | "quasiquote" => Some(s.as_ref()),
+                            "def" | "let" | "letf" | "quote" | "fn" | "if" | "and" | "or"
+                            | "match" | "quasiquote" | "unquote" | "unquote-splicing" => {
+                                Some(s.as_ref())
+                            }
                             _ => None,
                         },
                         _ => None,
@@ -56,45 +73,27 @@ impl Sexpr {
     }
 
     pub fn as_atom(&self) -> Option<Atom> {
-        match *self.kind {
-            SexprKind::Atom(a) => Some(a),
+        match &*self.kind {
+            SexprKind::Atom(a) => Some(a.clone()),
             _ => None,
         }
     }
 
     pub fn as_list(&self) -> Option<List<Sexpr>> {
-        match *self.kind {
-            SexprKind::List(l) => Some(l),
+        match &*self.kind {
+            SexprKind::List(l) => Some(l.clone()),
             _ => None,
         }
     }
 
     pub fn replace(&mut self, kind: SexprKind) {
-        self.kind = Box::new(kind);
+        *self.kind = kind;
     }
 
+    /// Substitutes `arg` for the free occurrences of `sym`, see
+    /// [`Sexpr::subst`].
     pub fn replace_sym(&mut self, sym: InternedString, arg: Sexpr) {
-        // recursively replace all instances of the symbol
-        match *self.kind {
-            SexprKind::Atom(a) => match *a.kind {
-                AtomKind::Sym(s) => {
-                    if s == sym {
-                        *self = arg;
-                    }
-                }
-                _ => (),
-            },
-            SexprKind::List(l) => {
-                let mut new_vec = vec![];
-                for s in l.iter() {
-                    let mut new_s = s.clone();
-                    new_s.replace_sym(sym.clone(), arg.clone());
-                    new_vec.push(new_s);
-                }
-                let new_list = List::from(new_vec);
-                *self = Sexpr::new(SexprKind::List(new_list), self.span);
-            }
-        }
+        *self = self.subst(sym, &arg);
     }
 }
 
@@ -110,6 +109,10 @@ impl Eq for Sexpr {}
 pub enum SexprKind {
     Atom(Atom),
     List(List<Sexpr>),
+    DataList(Vec<Sexpr>),
+    Vector(Vec<Sexpr>),
+    Map(Vec<(Sexpr, Sexpr)>),
+    Set(Vec<Sexpr>),
 }
 
 impl Display for SexprKind {
@@ -117,10 +120,45 @@ impl Display for SexprKind {
         match self {
             SexprKind::Atom(a) => write!(f, "{}", a),
             SexprKind::List(l) => write!(f, "{}", l),
+            SexprKind::DataList(v) => {
+                write!(f, "[")?;
+                write_seq(f, v)?;
+                write!(f, "]")
+            }
+            SexprKind::Vector(v) => {
+                write!(f, "#[")?;
+                write_seq(f, v)?;
+                write!(f, "]")
+            }
+            SexprKind::Map(m) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{} {}", k, v)?;
+                }
+                write!(f, "}}")
+            }
+            SexprKind::Set(v) => {
+                write!(f, "#{{")?;
+                write_seq(f, v)?;
+                write!(f, "}}")
+            }
         }
     }
 }
 
+fn write_seq(f: &mut std::fmt::Formatter<'_>, sexprs: &[Sexpr]) -> std::fmt::Result {
+    for (i, s) in sexprs.iter().enumerate() {
+        if i != 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", s)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Atom {
     pub kind: Box<AtomKind>,
@@ -135,16 +173,24 @@ impl Atom {
         }
     }
 
+    pub fn kind(&self) -> &AtomKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
     pub fn as_lit(&self) -> Option<Lit> {
-        match *self.kind {
-            AtomKind::Lit(l) => Some(l),
+        match &*self.kind {
+            AtomKind::Lit(l) => Some(l.clone()),
             _ => None,
         }
     }
 
     pub fn as_sym(&self) -> Option<InternedString> {
-        match *self.kind {
-            AtomKind::Sym(s) => Some(s),
+        match &*self.kind {
+            AtomKind::Sym(s) => Some(*s),
             _ => None,
         }
     }
@@ -160,6 +206,9 @@ impl Display for Atom {
 pub enum AtomKind {
     Lit(Lit),
     Sym(InternedString),
+    Keyword(InternedString),
+    // a.b.c, either a module-qualified name or a record field access
+    Path(Vec<InternedString>),
 }
 
 impl Display for AtomKind {
@@ -167,6 +216,16 @@ impl Display for AtomKind {
         match self {
             AtomKind::Lit(l) => write!(f, "{}", l),
             AtomKind::Sym(s) => write!(f, "{}", s),
+            AtomKind::Keyword(k) => write!(f, ":{}", k),
+            AtomKind::Path(p) => {
+                for (i, s) in p.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{}", s)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -181,6 +240,7 @@ pub enum Lit {
     String(InternedString),
     Bool(bool),
     Char(char),
+    Bytes(Vec<u8>),
 }
 
 impl Display for Lit {
@@ -191,9 +251,40 @@ impl Display for Lit {
             Lit::Real(r) => write!(f, "{}", r),
             Lit::Rational(r) => write!(f, "{}", r),
             Lit::BigRational(r) => write!(f, "{}", r),
-            Lit::String(s) => write!(f, "{}", s),
+            Lit::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\0' => write!(f, "\\0")?,
+                        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Lit::Bool(b) => write!(f, "{}", b),
-            Lit::Char(c) => write!(f, "{}", c),
+            Lit::Char(c) => match CHAR_NAMES.iter().find(|(_, ch)| ch == c) {
+                Some((name, _)) => write!(f, "#\\{}", name),
+                None if c.is_control() || c.is_whitespace() => {
+                    write!(f, "#\\x{:x}", *c as u32)
+                }
+                None => write!(f, "#\\{}", c),
+            },
+            Lit::Bytes(b) => {
+                write!(f, "#u8(")?;
+                for (i, byte) in b.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
@@ -0,0 +1,811 @@
+/*
+ * Binding-aware operations on sexprs: free variables, capture-avoiding
+ * substitution and span-insensitive structural and alpha-equivalence.
+ *
+ * Binders are recognised syntactically from the special forms that
+ * introduce them:
+ *
+ *   (fn params body...)
+ *   (let ((pat expr)...) body...)
+ *   (let name ((pat expr)...) body...)
+ *   (letf (name params...) fbody body...)
+ *   (def (name params...) body...)
+ *   (def pat expr)
+ *   (match expr (pat body...)...)
+ *
+ * `quote`d data is left alone, and inside `quasiquote` only the code
+ * `unquote`d back to depth zero is treated as code.
+ */
+use super::{
+    sexpr::{Atom, AtomKind, Sexpr, SexprKind},
+    visit::{walk_sexpr, Visitor, VisitorMut},
+};
+use lust_utils::{intern::InternedString, list::List, span::Span};
+use std::collections::HashSet;
+
+impl Sexpr {
+    /// Variables referenced by the code in `self` that aren't bound in it.
+    /// A path like `m.a` references `m`.
+    pub fn free_vars(&self) -> HashSet<InternedString> {
+        let mut vars = HashSet::new();
+        free_vars(self, &mut vars);
+        vars
+    }
+
+    /// Replaces the free occurrences of `sym` with `arg`, renaming binders
+    /// that would otherwise capture free variables of `arg`.
+    pub fn subst(&self, sym: InternedString, arg: &Sexpr) -> Sexpr {
+        let mut sexpr = self.clone();
+        subst(&mut sexpr, sym, arg, &arg.free_vars());
+        sexpr
+    }
+
+    /// Structural equality that ignores spans.
+    pub fn struct_eq(&self, other: &Sexpr) -> bool {
+        match (self.kind(), other.kind()) {
+            (SexprKind::Atom(a), SexprKind::Atom(b)) => a.kind == b.kind,
+            (SexprKind::List(a), SexprKind::List(b)) => {
+                seq_eq(a.iter(), b.iter())
+                    && match (a.dotted_tail(), b.dotted_tail()) {
+                        (Some(a), Some(b)) => a.struct_eq(b),
+                        (a, b) => a.is_none() && b.is_none(),
+                    }
+            }
+            (SexprKind::DataList(a), SexprKind::DataList(b))
+            | (SexprKind::Vector(a), SexprKind::Vector(b))
+            | (SexprKind::Set(a), SexprKind::Set(b)) => seq_eq(a.iter(), b.iter()),
+            (SexprKind::Map(a), SexprKind::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((ak, av), (bk, bv))| ak.struct_eq(bk) && av.struct_eq(bv))
+            }
+            _ => false,
+        }
+    }
+
+    /// Structural equality up to the names of bound variables, so
+    /// `(fn (x) x)` is alpha-equivalent to `(fn (y) y)`.
+    pub fn alpha_eq(&self, other: &Sexpr) -> bool {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        canonicalize(&mut a, &mut 0);
+        canonicalize(&mut b, &mut 0);
+        a.struct_eq(&b)
+    }
+}
+
+fn seq_eq<'a>(
+    mut a: impl Iterator<Item = &'a Sexpr>,
+    mut b: impl Iterator<Item = &'a Sexpr>,
+) -> bool {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) if a.struct_eq(b) => (),
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+// Where a sub-sexpr of a binding form sits relative to the form's scopes.
+// Scopes are numbered per form.
+#[derive(Debug)]
+enum Role {
+    // binds all its variables in the scope
+    Pattern(usize),
+    // `(name params...)` binds `name` in one scope and the params in another
+    Signature { name: usize, params: usize },
+    // code that sees the binders of the scopes, outermost first
+    Expr(Vec<usize>),
+}
+
+#[derive(Debug)]
+struct Slot {
+    path: Vec<usize>,
+    role: Role,
+}
+
+impl Slot {
+    fn new(path: Vec<usize>, role: Role) -> Self {
+        Self { path, role }
+    }
+}
+
+fn layout(form: &Sexpr) -> Option<Vec<Slot>> {
+    let SexprKind::List(list) = form.kind() else {
+        return None;
+    };
+    let items = list.iter().collect::<Vec<_>>();
+    let head = items.first()?.as_atom()?.as_sym()?;
+    let is_sym = |i: usize| items[i].as_atom().is_some_and(|a| a.as_sym().is_some());
+    let is_list = |i: usize| matches!(items[i].kind(), SexprKind::List(_));
+    let body = |from: usize, scopes: Vec<usize>| {
+        (from..items.len()).map(move |i| Slot::new(vec![i], Role::Expr(scopes.clone())))
+    };
+    let mut slots = vec![];
+    match (&*head, items.len()) {
+        ("fn", 2..) => {
+            slots.push(Slot::new(vec![1], Role::Pattern(0)));
+            slots.extend(body(2, vec![0]));
+        }
+        ("let", 3..) if is_sym(1) => {
+            slots.push(Slot::new(vec![1], Role::Pattern(0)));
+            let_bindings(items[2], 2, false, &mut slots);
+            slots.extend(body(3, vec![0]));
+        }
+        ("let", 2..) => {
+            let scopes = let_bindings(items[1], 1, true, &mut slots);
+            slots.extend(body(2, (0..scopes).collect()));
+        }
+        ("letf", 3..) if is_list(1) => {
+            slots.push(Slot::new(vec![1], Role::Signature { name: 0, params: 1 }));
+            slots.push(Slot::new(vec![2], Role::Expr(vec![0, 1])));
+            slots.extend(body(3, vec![0]));
+        }
+        ("def", 3..) if is_list(1) => {
+            slots.push(Slot::new(vec![1], Role::Signature { name: 0, params: 0 }));
+            slots.extend(body(2, vec![0]));
+        }
+        ("def", 3) => {
+            slots.push(Slot::new(vec![1], Role::Pattern(0)));
+            slots.push(Slot::new(vec![2], Role::Expr(vec![])));
+        }
+        ("match", 2..) => {
+            slots.push(Slot::new(vec![1], Role::Expr(vec![])));
+            for (i, clause) in items.iter().enumerate().skip(2) {
+                let SexprKind::List(clause) = clause.kind() else {
+                    slots.push(Slot::new(vec![i], Role::Expr(vec![])));
+                    continue;
+                };
+                // each clause is its own scope; in `(pat when guard body...)`
+                // the `when` marker is neither pattern nor code
+                let len = clause.iter().count();
+                let guarded =
+                    len >= 4 && clause.iter().nth(1).and_then(as_sym).as_deref() == Some("when");
+                for j in 0..len {
+                    if guarded && j == 1 {
+                        continue;
+                    }
+                    let role = if j == 0 {
+                        Role::Pattern(i)
+                    } else {
+                        Role::Expr(vec![i])
+                    };
+                    slots.push(Slot::new(vec![i, j], role));
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(slots)
+}
+
+// ((pat expr)...), returning the number of scopes the patterns bind.
+// Sequential bindings nest like `let`, so binding j gets scope j and its
+// expression sees the patterns before it. Otherwise, as for the initial
+// values of a named let, the patterns all go in scope 0 and no expression
+// sees them.
+fn let_bindings(bindings: &Sexpr, i: usize, sequential: bool, slots: &mut Vec<Slot>) -> usize {
+    let bindings = match bindings.kind() {
+        SexprKind::List(l) => l.iter().collect(),
+        SexprKind::DataList(v) => v.iter().collect(),
+        _ => vec![],
+    };
+    if bindings.is_empty() {
+        slots.push(Slot::new(vec![i], Role::Expr(vec![])));
+    }
+    let count = bindings.len();
+    for (j, binding) in bindings.into_iter().enumerate() {
+        let (scope, outer) = if sequential {
+            (j, (0..j).collect())
+        } else {
+            (0, vec![])
+        };
+        match binding.kind() {
+            SexprKind::List(l) if l.iter().count() == 2 => {
+                slots.push(Slot::new(vec![i, j, 0], Role::Pattern(scope)));
+                slots.push(Slot::new(vec![i, j, 1], Role::Expr(outer)));
+            }
+            _ => slots.push(Slot::new(vec![i, j], Role::Expr(outer))),
+        }
+    }
+    if sequential {
+        count
+    } else {
+        1
+    }
+}
+
+fn scope_count(slots: &[Slot]) -> usize {
+    slots
+        .iter()
+        .map(|slot| match &slot.role {
+            Role::Pattern(s) => s + 1,
+            Role::Signature { name, params } => name.max(params) + 1,
+            Role::Expr(scopes) => scopes.iter().max().map_or(0, |s| s + 1),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// The variables bound by `scope`, in source order.
+fn scope_vars(form: &Sexpr, slots: &[Slot], scope: usize) -> Vec<InternedString> {
+    let mut vars = vec![];
+    for slot in slots {
+        let Some(sexpr) = at(form, &slot.path) else {
+            continue;
+        };
+        match slot.role {
+            Role::Pattern(s) if s == scope => pattern_vars(sexpr, &mut vars),
+            Role::Signature { name, params } => {
+                let SexprKind::List(sig) = sexpr.kind() else {
+                    continue;
+                };
+                if name == scope {
+                    vars.extend(sig.head().and_then(as_sym));
+                }
+                if params == scope {
+                    sig.iter()
+                        .skip(1)
+                        .chain(sig.dotted_tail())
+                        .for_each(|p| pattern_vars(p, &mut vars));
+                }
+            }
+            _ => (),
+        }
+    }
+    vars
+}
+
+fn pattern_vars(pat: &Sexpr, vars: &mut Vec<InternedString>) {
+    match pat.kind() {
+        SexprKind::Atom(a) => match a.as_sym() {
+            Some(s) if &*s != "_" && !vars.contains(&s) => vars.push(s),
+            _ => (),
+        },
+        SexprKind::List(l) => {
+            let skip = match l.head().and_then(as_sym).as_deref() {
+                Some("quote") => return,
+                // alternatives of `or` bind the same names
+                Some("varg" | "as" | "or") => 1,
+                _ => 0,
+            };
+            l.iter()
+                .skip(skip)
+                .chain(l.dotted_tail())
+                .for_each(|p| pattern_vars(p, vars));
+        }
+        SexprKind::DataList(v) | SexprKind::Vector(v) => {
+            v.iter().for_each(|p| pattern_vars(p, vars))
+        }
+        // keys are matched literally
+        SexprKind::Map(m) => m.iter().for_each(|(_, p)| pattern_vars(p, vars)),
+        SexprKind::Set(_) => (),
+    }
+}
+
+fn free_vars(sexpr: &Sexpr, vars: &mut HashSet<InternedString>) {
+    match sexpr.kind() {
+        SexprKind::Atom(a) => match a.kind() {
+            AtomKind::Sym(s) => {
+                vars.insert(*s);
+            }
+            AtomKind::Path(p) => {
+                vars.insert(p[0]);
+            }
+            _ => (),
+        },
+        _ => match form_head(sexpr).as_deref() {
+            Some("quote") => (),
+            Some("quasiquote") => {
+                for code in unquoted(sexpr, 0) {
+                    free_vars(code, vars);
+                }
+            }
+            _ => match layout(sexpr) {
+                Some(slots) => {
+                    for slot in &slots {
+                        let (Role::Expr(scopes), Some(code)) = (&slot.role, at(sexpr, &slot.path))
+                        else {
+                            continue;
+                        };
+                        let mut code_vars = HashSet::new();
+                        free_vars(code, &mut code_vars);
+                        for &scope in scopes {
+                            for var in scope_vars(sexpr, &slots, scope) {
+                                code_vars.remove(&var);
+                            }
+                        }
+                        vars.extend(code_vars);
+                    }
+                }
+                None => children(sexpr).for_each(|child| free_vars(child, vars)),
+            },
+        },
+    }
+}
+
+fn subst(sexpr: &mut Sexpr, sym: InternedString, arg: &Sexpr, arg_vars: &HashSet<InternedString>) {
+    match &*sexpr.kind {
+        SexprKind::Atom(a) => match a.kind() {
+            AtomKind::Sym(s) if *s == sym => *sexpr = arg.clone(),
+            AtomKind::Path(p) if p[0] == sym => {
+                *sexpr = field_access(arg, &p[1..], sexpr.span);
+            }
+            _ => (),
+        },
+        _ => match form_head(sexpr).as_deref() {
+            Some("quote") => (),
+            Some("quasiquote") => {
+                for code in unquoted_mut(sexpr, 0) {
+                    subst(code, sym, arg, arg_vars);
+                }
+            }
+            _ => match layout(sexpr) {
+                Some(slots) => subst_form(sexpr, &slots, sym, arg, arg_vars),
+                None => children_mut(sexpr).for_each(|child| subst(child, sym, arg, arg_vars)),
+            },
+        },
+    }
+}
+
+fn subst_form(
+    form: &mut Sexpr,
+    slots: &[Slot],
+    sym: InternedString,
+    arg: &Sexpr,
+    arg_vars: &HashSet<InternedString>,
+) {
+    // rename the binders that would capture free variables of `arg`, in
+    // the scopes `sym` is substituted into
+    for scope in 0..scope_count(slots) {
+        let vars = scope_vars(form, slots, scope);
+        if vars.contains(&sym) || !free_in_scope(form, slots, scope, sym) {
+            continue;
+        }
+        for var in vars.into_iter().filter(|var| arg_vars.contains(var)) {
+            let mut avoid = arg_vars.clone();
+            all_syms(form, &mut avoid);
+            let fresh = fresh(var, &avoid);
+            rename(form, slots, scope, var, fresh);
+        }
+    }
+    let shadowed = (0..scope_count(slots))
+        .filter(|&scope| scope_vars(form, slots, scope).contains(&sym))
+        .collect::<Vec<_>>();
+    for slot in slots {
+        let Role::Expr(scopes) = &slot.role else {
+            continue;
+        };
+        if scopes.iter().any(|scope| shadowed.contains(scope)) {
+            continue;
+        }
+        if let Some(code) = at_mut(form, &slot.path) {
+            subst(code, sym, arg, arg_vars);
+        }
+    }
+}
+
+fn free_in_scope(form: &Sexpr, slots: &[Slot], scope: usize, sym: InternedString) -> bool {
+    slots.iter().any(|slot| match &slot.role {
+        Role::Expr(scopes) if scopes.contains(&scope) => {
+            at(form, &slot.path).is_some_and(|code| code.free_vars().contains(&sym))
+        }
+        _ => false,
+    })
+}
+
+// Renames the binder `from` of `scope` and its references to `to`, which
+// must not occur in the form.
+fn rename(
+    form: &mut Sexpr,
+    slots: &[Slot],
+    scope: usize,
+    from: InternedString,
+    to: InternedString,
+) {
+    // references in code that an inner scope of the same form rebinds
+    // don't belong to `scope`
+    let rebound = (0..scope_count(slots))
+        .filter(|&s| s != scope && scope_vars(form, slots, s).contains(&from))
+        .collect::<Vec<_>>();
+    for slot in slots {
+        let Some(sexpr) = at_mut(form, &slot.path) else {
+            continue;
+        };
+        match &slot.role {
+            Role::Pattern(s) if *s == scope => rename_syms(sexpr, from, to),
+            Role::Signature { name, params } => {
+                let SexprKind::List(sig) = &mut *sexpr.kind else {
+                    continue;
+                };
+                let mut iter = sig.iter_mut();
+                if let Some(head) = iter.next() {
+                    if *name == scope {
+                        rename_syms(head, from, to);
+                    }
+                }
+                if *params == scope {
+                    iter.for_each(|p| rename_syms(p, from, to));
+                    if let Some(tail) = sig.dotted_tail_mut() {
+                        rename_syms(tail, from, to);
+                    }
+                }
+            }
+            Role::Expr(scopes) => {
+                let Some(pos) = scopes.iter().position(|&s| s == scope) else {
+                    continue;
+                };
+                if scopes[pos + 1..].iter().any(|s| rebound.contains(s)) {
+                    continue;
+                }
+                let to = Sexpr::new(
+                    SexprKind::Atom(Atom::new(AtomKind::Sym(to), sexpr.span)),
+                    sexpr.span,
+                );
+                subst(sexpr, from, &to, &to.free_vars());
+            }
+            _ => (),
+        }
+    }
+}
+
+// Renames every symbol `from` in a pattern, keeping spans.
+fn rename_syms(pat: &mut Sexpr, from: InternedString, to: InternedString) {
+    struct Rename(InternedString, InternedString);
+
+    impl VisitorMut for Rename {
+        fn visit_atom_mut(&mut self, atom: &mut Atom) {
+            if *atom.kind == AtomKind::Sym(self.0) {
+                *atom.kind = AtomKind::Sym(self.1);
+            }
+        }
+    }
+
+    Rename(from, to).visit_sexpr_mut(pat)
+}
+
+// Every symbol in the tree, bound, free or quoted, plus path heads.
+fn all_syms(sexpr: &Sexpr, syms: &mut HashSet<InternedString>) {
+    struct Syms<'a>(&'a mut HashSet<InternedString>);
+
+    impl Visitor for Syms<'_> {
+        fn visit_atom(&mut self, atom: &Atom) {
+            match atom.kind() {
+                AtomKind::Sym(s) => {
+                    self.0.insert(*s);
+                }
+                AtomKind::Path(p) => {
+                    self.0.insert(p[0]);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    walk_sexpr(&mut Syms(syms), sexpr)
+}
+
+// x -> x1, x2, ...
+fn fresh(var: InternedString, avoid: &HashSet<InternedString>) -> InternedString {
+    (1..)
+        .map(|n| InternedString::from(format!("{}{}", var, n)))
+        .find(|name| !avoid.contains(name))
+        .unwrap()
+}
+
+// `m.a.b` with `m` replaced by `arg`: still a path when `arg` is a name,
+// otherwise `(Map.get (Map.get arg :a) :b)`.
+fn field_access(arg: &Sexpr, fields: &[InternedString], span: Span) -> Sexpr {
+    let atom = |kind| Sexpr::new(SexprKind::Atom(Atom::new(kind, span)), span);
+    if let SexprKind::Atom(a) = arg.kind() {
+        match a.kind() {
+            AtomKind::Sym(s) => {
+                return atom(AtomKind::Path([*s].iter().chain(fields).copied().collect()))
+            }
+            AtomKind::Path(p) => {
+                return atom(AtomKind::Path(p.iter().chain(fields).copied().collect()))
+            }
+            _ => (),
+        }
+    }
+    let get = InternedString::from("get");
+    fields.iter().fold(arg.clone(), |record, field| {
+        Sexpr::new(
+            SexprKind::List(List::from([
+                atom(AtomKind::Path(vec!["Map".into(), get])),
+                record,
+                atom(AtomKind::Keyword(*field)),
+            ])),
+            span,
+        )
+    })
+}
+
+// Renames every binder to `#0`, `#1`, ... in traversal order. `#` can't
+// start a symbol, so the new names can't clash with the source.
+fn canonicalize(sexpr: &mut Sexpr, next: &mut usize) {
+    if let SexprKind::Atom(_) = sexpr.kind() {
+        return;
+    }
+    match form_head(sexpr).as_deref() {
+        Some("quote") => (),
+        Some("quasiquote") => {
+            for code in unquoted_mut(sexpr, 0) {
+                canonicalize(code, next);
+            }
+        }
+        _ => match layout(sexpr) {
+            Some(slots) => {
+                for scope in 0..scope_count(&slots) {
+                    for var in scope_vars(sexpr, &slots, scope) {
+                        let name = InternedString::from(format!("#{}", next));
+                        *next += 1;
+                        rename(sexpr, &slots, scope, var, name);
+                    }
+                }
+                for slot in &slots {
+                    if let (Role::Expr(_), Some(code)) = (&slot.role, at_mut(sexpr, &slot.path)) {
+                        canonicalize(code, next);
+                    }
+                }
+            }
+            None => children_mut(sexpr).for_each(|child| canonicalize(child, next)),
+        },
+    }
+}
+
+fn form_head(sexpr: &Sexpr) -> Option<InternedString> {
+    match sexpr.kind() {
+        SexprKind::List(l) => l.head().and_then(as_sym),
+        _ => None,
+    }
+}
+
+fn as_sym(sexpr: &Sexpr) -> Option<InternedString> {
+    sexpr.as_atom()?.as_sym()
+}
+
+// The code inside a quasiquoted template, `depth` quasiquotes deep.
+fn unquoted(sexpr: &Sexpr, depth: usize) -> Vec<&Sexpr> {
+    let depth = match form_head(sexpr).as_deref() {
+        Some("quasiquote") => depth + 1,
+        Some("unquote" | "unquote-splicing") if depth == 1 => {
+            return children(sexpr).skip(1).collect();
+        }
+        Some("unquote" | "unquote-splicing") => depth - 1,
+        _ => depth,
+    };
+    children(sexpr)
+        .flat_map(|child| unquoted(child, depth))
+        .collect()
+}
+
+fn unquoted_mut(sexpr: &mut Sexpr, depth: usize) -> Vec<&mut Sexpr> {
+    let depth = match form_head(sexpr).as_deref() {
+        Some("quasiquote") => depth + 1,
+        Some("unquote" | "unquote-splicing") if depth == 1 => {
+            return children_mut(sexpr).skip(1).collect();
+        }
+        Some("unquote" | "unquote-splicing") => depth - 1,
+        _ => depth,
+    };
+    children_mut(sexpr)
+        .flat_map(|child| unquoted_mut(child, depth))
+        .collect()
+}
+
+fn children(sexpr: &Sexpr) -> impl Iterator<Item = &Sexpr> {
+    let children: Box<dyn Iterator<Item = &Sexpr>> = match sexpr.kind() {
+        SexprKind::Atom(_) => Box::new(std::iter::empty()),
+        SexprKind::List(l) => Box::new(l.iter().chain(l.dotted_tail())),
+        SexprKind::DataList(v) | SexprKind::Vector(v) | SexprKind::Set(v) => Box::new(v.iter()),
+        SexprKind::Map(m) => Box::new(m.iter().flat_map(|(k, v)| [k, v])),
+    };
+    children
+}
+
+fn children_mut(sexpr: &mut Sexpr) -> impl Iterator<Item = &mut Sexpr> {
+    let children: Vec<&mut Sexpr> = match &mut *sexpr.kind {
+        SexprKind::Atom(_) => vec![],
+        SexprKind::List(l) => {
+            // the tail isn't reachable from `iter_mut`, so split it off
+            // by hand
+            let mut children = vec![];
+            let mut list = l;
+            loop {
+                match list {
+                    List::Pair { head, tail } => {
+                        children.push(head);
+                        list = tail;
+                    }
+                    List::Dotted(tail) => {
+                        children.push(tail);
+                        break;
+                    }
+                    List::Empty => break,
+                }
+            }
+            children
+        }
+        SexprKind::DataList(v) | SexprKind::Vector(v) | SexprKind::Set(v) => v.iter_mut().collect(),
+        SexprKind::Map(m) => m.iter_mut().flat_map(|(k, v)| [k, v]).collect(),
+    };
+    children.into_iter()
+}
+
+fn at<'a>(sexpr: &'a Sexpr, path: &[usize]) -> Option<&'a Sexpr> {
+    path.iter().try_fold(sexpr, |sexpr, &i| match sexpr.kind() {
+        SexprKind::List(l) => l.iter().nth(i),
+        SexprKind::DataList(v) | SexprKind::Vector(v) => v.get(i),
+        _ => None,
+    })
+}
+
+fn at_mut<'a>(sexpr: &'a mut Sexpr, path: &[usize]) -> Option<&'a mut Sexpr> {
+    path.iter()
+        .try_fold(sexpr, |sexpr, &i| match &mut *sexpr.kind {
+            SexprKind::List(l) => l.iter_mut().nth(i),
+            SexprKind::DataList(v) | SexprKind::Vector(v) => v.get_mut(i),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::read::{read, sexpr::Sexpr};
+    use lust_utils::intern::InternedString;
+
+    fn sexpr(src: &str) -> Sexpr {
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        root.sexprs[0].clone()
+    }
+
+    fn subst(src: &str, sym: &str, arg: &str) -> String {
+        sexpr(src)
+            .subst(InternedString::from(sym), &sexpr(arg))
+            .to_string()
+    }
+
+    fn vars(src: &str) -> Vec<String> {
+        let mut vars = sexpr(src)
+            .free_vars()
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        vars.sort();
+        vars
+    }
+
+    #[test]
+    fn free_vars() {
+        assert_eq!(vars("(f x 'y `(z ,w))"), ["f", "w", "x"]);
+        assert_eq!(vars("(fn (x . xs) (g x xs y))"), ["g", "y"]);
+        assert_eq!(vars("(let ((x y) (z x)) (+ x z))"), ["+", "y"]);
+        assert_eq!(vars("(let ((z x) (x y)) (+ x z))"), ["+", "x", "y"]);
+        assert_eq!(vars("(let loop ((i n)) (loop i))"), ["n"]);
+        assert_eq!(vars("(letf (f a) (f a b) (f c))"), ["b", "c"]);
+        assert_eq!(
+            vars("(match xs ((y ys...) (h y ys)) (_ d))"),
+            ["d", "h", "xs"]
+        );
+        assert_eq!(vars("(def (f x) (g x m.a))"), ["g", "m"]);
+        assert_eq!(
+            vars("(match v ((as (or (a) [a]) all) (f a all)))"),
+            ["f", "v"]
+        );
+        assert_eq!(vars("(match v (n when (> n 0) n))"), [">", "v"]);
+    }
+
+    #[test]
+    fn subst_free_occurrences() {
+        assert_eq!(subst("(+ x [x {:k x}])", "x", "1"), "(+ 1 [1 {:k 1}])");
+        assert_eq!(subst("(f . x)", "x", "y"), "(f . y)");
+        assert_eq!(subst("(fn (x) x)", "x", "1"), "(fn (x) x)");
+        assert_eq!(subst("(fn (y) (x y))", "x", "z"), "(fn (y) (z y))");
+        assert_eq!(
+            subst("(match x (x x) (y x))", "x", "1"),
+            "(match 1 (x x) (y 1))"
+        );
+        assert_eq!(
+            subst("(match v (n when (p when) n))", "when", "w"),
+            "(match v (n when (p w) n))"
+        );
+        assert_eq!(
+            subst("(let ((x x)) (g x))", "x", "1"),
+            "(let ((x 1)) (g x))"
+        );
+        assert_eq!(
+            subst("(let ((y x) (x y)) x)", "x", "1"),
+            "(let ((y 1) (x y)) x)"
+        );
+    }
+
+    #[test]
+    fn let_scopes_bindings_sequentially() {
+        assert_eq!(vars("(let ((a b) (c a) (b c)) (f a b c))"), ["b", "f"]);
+        assert_eq!(
+            subst("(let ((a x) (x a) (b x)) (f x b))", "x", "1"),
+            "(let ((a 1) (x a) (b x)) (f x b))"
+        );
+        assert_eq!(
+            subst("(let ((y x) (z y)) (g x z))", "x", "y"),
+            "(let ((y1 y) (z y1)) (g y z))"
+        );
+    }
+
+    #[test]
+    fn subst_respects_quote() {
+        assert_eq!(
+            subst("(f 'x (quote (x)))", "x", "1"),
+            "(f (quote x) (quote (x)))"
+        );
+        assert_eq!(
+            subst("`(x ,x `(x ,x ,,x))", "x", "1"),
+            "(quasiquote (x (unquote 1) (quasiquote (x (unquote x) (unquote (unquote 1))))))"
+        );
+    }
+
+    #[test]
+    fn guarded_arm_when_is_not_code() {
+        assert_eq!(
+            vars("(match v (x when (p x when) (f x)))"),
+            ["f", "p", "v", "when"]
+        );
+        // too short to have a guard, so `when` is the body
+        assert_eq!(vars("(match v (n when))"), ["v", "when"]);
+        assert_eq!(
+            subst("(match v (x when (p x when) when))", "when", "w"),
+            "(match v (x when (p x w) w))"
+        );
+    }
+
+    #[test]
+    fn subst_avoids_capture() {
+        assert_eq!(subst("(fn (y) (x y))", "x", "y"), "(fn (y1) (y y1))");
+        assert_eq!(
+            subst("(let ((y 1) (y1 2)) (+ x y y1))", "x", "(f y)"),
+            "(let ((y2 1) (y1 2)) (+ (f y) y2 y1))"
+        );
+        assert_eq!(
+            subst("(letf (f a) (g a x) (f x))", "x", "a"),
+            "(letf (f a1) (g a1 a) (f a))"
+        );
+        // later bindings see earlier ones, so `y` would capture here too
+        assert_eq!(
+            subst("(let ((y 1) (z x)) z)", "x", "y"),
+            "(let ((y1 1) (z y)) z)"
+        );
+        // no renaming when nothing is substituted under the binder
+        assert_eq!(subst("(fn (y) y)", "x", "y"), "(fn (y) y)");
+    }
+
+    #[test]
+    fn subst_into_paths() {
+        assert_eq!(subst("(f m.a)", "m", "n"), "(f n.a)");
+        assert_eq!(
+            subst("m.a.b", "m", "{:a 1}"),
+            "(Map.get (Map.get {:a 1} :a) :b)"
+        );
+    }
+
+    #[test]
+    fn struct_eq_ignores_spans() {
+        assert!(sexpr("(a  (b . c)  {:k 1})").struct_eq(&sexpr("(a (b . c) {:k 1})")));
+        assert!(!sexpr("(a b)").struct_eq(&sexpr("(a . b)")));
+        assert!(!sexpr("[a b]").struct_eq(&sexpr("#[a b]")));
+    }
+
+    #[test]
+    fn alpha_eq() {
+        let eq = |a: &str, b: &str| sexpr(a).alpha_eq(&sexpr(b));
+        assert!(eq("(fn (x) x)", "(fn (y) y)"));
+        assert!(eq("(fn (x . xs) (f x xs))", "(fn (a . b) (f a b))"));
+        assert!(eq("(let loop ((i 0)) (loop i))", "(let go ((j 0)) (go j))"));
+        assert!(eq("(match v ((a b) a))", "(match v ((c d) c))"));
+        assert!(!eq("(fn (x) x)", "(fn (x) y)"));
+        assert!(!eq("(fn (x y) x)", "(fn (x y) y)"));
+        assert!(!eq("'(fn (x) x)", "'(fn (y) y)"));
+    }
+}
@@ -1,39 +1,58 @@
-use logos::Logos;
-use lust_utils::{intern::InternedString, num::{Int, Rational, Real}};
+use logos::{Lexer, Logos};
+use lust_utils::{
+    intern::InternedString,
+    num::{Fraction, Integer, Real},
+    span::Span,
+};
 use std::fmt::{Debug, Display};
 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum LexError {
+    #[default]
+    InvalidToken,
+    InvalidEscape(Span),
+    UnterminatedComment(Span),
+}
+
 #[derive(Logos, Debug, Clone, Default, PartialEq)]
+#[logos(error = LexError)]
 pub enum Token {
     Eof,
     #[default]
     Error,
-    #[regex(r"[ \t\r\n\f]+", logos::skip)]
+    #[regex(r"[ \t\r\n\f]+")]
     Whitespace,
     #[regex(r#";[^\n]*"#)]
     Comment,
-    #[regex(r#"[^.'\d\[\]()\s,{};][^.'\[\]()\s,{};]*"#, |lex| InternedString::from(lex.slice()))]
+    #[token("#|", lex_block_comment)]
+    BlockComment,
+    #[regex(r#"[^.'"\d\[\]()\s,{};:#`][^.'"\[\]()\s,{};]*"#, |lex| InternedString::from(lex.slice()))]
     Ident(InternedString),
+    #[regex(r#":[^.'"\[\]()\s,{};:][^.'"\[\]()\s,{};]*"#, |lex| InternedString::from(&lex.slice()[1..]))]
+    Keyword(InternedString),
     #[regex(
-        r#"(0b[0-1]+)|(0o[0-7]+)|(0x[0-9a-fA-F]+)|([1-9]\d*|0)"#, 
-        priority = 2, 
-        callback = |lex| lex.slice().parse::<Int>().ok()
+        r#"[+-]?((0b[01][01_]*)|(0o[0-7][0-7_]*)|(0x[0-9a-fA-F][0-9a-fA-F_]*)|([1-9][0-9_]*|0))"#,
+        priority = 3,
+        callback = |lex| lex.slice().parse::<Integer>().ok()
     )]
-    Int(Int),
+    Int(Integer),
     #[regex(
-        r#"([1-9]\d*|0)(\.\d+)?([eE][+-]?\d+)?"#, 
-        priority = 1, 
+        r#"([+-]?([1-9][0-9_]*|0)(\.[0-9][0-9_]*)?([eE][+-]?[0-9]+)?)|([+-](inf|nan)\.0)"#,
+        priority = 2,
         callback = |lex| lex.slice().parse::<Real>().ok()
     )]
     Real(Real),
     #[regex(
-        r#"((0b[0-1]+)|(0o[0-7]+)|(0x[0-9a-fA-F]+)|([1-9]\d*|0))(/-?((0b[0-1]+)|(0o[0-7]+)|(0x[0-9a-fA-F]+)|([1-9]\d*|0)))?"#,
-        priority = 0,
-        callback = |lex| lex.slice().parse::<Rational>().ok()
+        r#"[+-]?((0b[01][01_]*)|(0o[0-7][0-7_]*)|(0x[0-9a-fA-F][0-9a-fA-F_]*)|([1-9][0-9_]*|0))/[+-]?((0b[01][01_]*)|(0o[0-7][0-7_]*)|(0x[0-9a-fA-F][0-9a-fA-F_]*)|([1-9][0-9_]*|0))"#,
+        priority = 2,
+        callback = |lex| lex.slice().parse::<Fraction>().ok()
     )]
-    Rational(Rational),
+    Rational(Fraction),
     #[regex(r"#t|#f", |lex| lex.slice() == "#t")]
     Bool(bool),
-    #[regex(r#""("[^"\\]*(?:\\.[^"\\]*)*")""#, |lex| InternedString::from(lex.slice()))]
+    #[regex(r#"#\\([a-zA-Z][a-zA-Z0-9]*|u\{[0-9a-fA-F]+\}|.)"#, |lex| lex_char(&lex.slice()[2..]))]
+    Char(char),
+    #[regex(r#""([^"\\]|\\(.|\n))*""#, lex_string)]
     String(InternedString),
 
     #[token("(")]
@@ -60,14 +79,135 @@ pub enum Token {
     CommaAt,
     #[token("#")]
     Hash,
+    #[token("#;")]
+    HashSemi,
     #[token("#[")]
     HashLBrack,
+    #[token("#{")]
+    HashLBrace,
+    #[token("#u8(")]
+    HashU8LParen,
     #[token("'")]
     Quote,
     #[token("`")]
     Backquote,
 }
 
+/// Names accepted after `#\` in a character literal, e.g. `#\space`.
+pub const CHAR_NAMES: &[(&str, char)] = &[
+    ("alarm", '\u{7}'),
+    ("backspace", '\u{8}'),
+    ("delete", '\u{7f}'),
+    ("escape", '\u{1b}'),
+    ("newline", '\n'),
+    ("null", '\0'),
+    ("return", '\r'),
+    ("space", ' '),
+    ("tab", '\t'),
+];
+
+// Decodes the text after `#\`: a single character, a name from
+// `CHAR_NAMES`, `x41` or `u{1F600}`.
+fn lex_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => {
+            if let Some((_, c)) = CHAR_NAMES.iter().find(|(name, _)| *name == s) {
+                Some(*c)
+            } else if let Some(hex) = s.strip_prefix('x') {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(hex) = s.strip_prefix("u{").and_then(|s| s.strip_suffix('}')) {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Skips to the `|#` that closes a block comment, counting any nested `#|`.
+fn lex_block_comment(lex: &mut Lexer<Token>) -> Result<(), LexError> {
+    let rest = lex.remainder().as_bytes();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest.get(i..i + 2) {
+            Some(b"#|") => {
+                depth += 1;
+                i += 2;
+            }
+            Some(b"|#") => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    lex.bump(i);
+                    return Ok(());
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    let start = lex.span().start;
+    lex.bump(rest.len());
+    Err(LexError::UnterminatedComment(Span::from(start..start + 2)))
+}
+
+// Decodes the escapes in a string literal. `\` followed by a line break
+// skips the break and any leading whitespace on the next line.
+fn lex_string(lex: &mut Lexer<Token>) -> Result<InternedString, LexError> {
+    let start = lex.span().start + 1;
+    let slice = lex.slice();
+    let src = &slice[1..slice.len() - 1];
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let invalid = |end: usize| LexError::InvalidEscape(Span::from(start + i..start + end));
+        match chars.next() {
+            Some((_, 'n')) => out.push('\n'),
+            Some((_, 't')) => out.push('\t'),
+            Some((_, 'r')) => out.push('\r'),
+            Some((_, '0')) => out.push('\0'),
+            Some((_, '\\')) => out.push('\\'),
+            Some((_, '"')) => out.push('"'),
+            Some((_, '\n' | '\r')) => {
+                while let Some((_, c)) = chars.peek() {
+                    if !c.is_whitespace() {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            Some((j, 'u')) => {
+                let mut end = j + 1;
+                if chars.next_if(|(_, c)| *c == '{').is_none() {
+                    return Err(invalid(end));
+                }
+                let mut hex = String::new();
+                while let Some((k, c)) = chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                    hex.push(c);
+                    end = k + 1;
+                }
+                match chars.next_if(|(_, c)| *c == '}') {
+                    Some((k, _)) => end = k + 1,
+                    None => return Err(invalid(end + 1)),
+                }
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => out.push(c),
+                    None => return Err(invalid(end)),
+                }
+            }
+            Some((j, c)) => return Err(invalid(j + c.len_utf8())),
+            None => return Err(invalid(i + 1)),
+        }
+    }
+    Ok(InternedString::from(out))
+}
+
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Token::*;
@@ -76,11 +216,14 @@ impl Display for Token {
             Error => write!(f, "Error"),
             Whitespace => write!(f, "Whitespace"),
             Comment => write!(f, "Comment"),
+            BlockComment => write!(f, "BlockComment"),
             Ident(name) => write!(f, "Ident({})", name),
+            Keyword(name) => write!(f, "Keyword(:{})", name),
             Int(n) => write!(f, "Int({})", n),
             Real(n) => write!(f, "Float({})", n),
             Rational(n) => write!(f, "Rational({})", n),
             Bool(b) => write!(f, "Bool({})", b),
+            Char(c) => write!(f, "Char({:?})", c),
             String(s) => write!(f, "String({})", s),
             LParen => write!(f, "("),
             RParen => write!(f, ")"),
@@ -94,7 +237,10 @@ impl Display for Token {
             Comma => write!(f, ","),
             CommaAt => write!(f, ",@"),
             Hash => write!(f, "#"),
+            HashSemi => write!(f, "#;"),
             HashLBrack => write!(f, "#["),
+            HashLBrace => write!(f, "#{{"),
+            HashU8LParen => write!(f, "#u8("),
             Quote => write!(f, "'"),
             Backquote => write!(f, "`"),
         }
@@ -0,0 +1,183 @@
+/*
+ * Generic traversals over `Sexpr` trees. Implementors override the hooks
+ * they care about and call the matching `walk_*`/`fold_children` function
+ * to keep descending; the defaults visit every sexpr in source order,
+ * including dotted tails and both halves of map entries.
+ */
+use super::sexpr::{Atom, Sexpr, SexprKind};
+use lust_utils::list::List;
+
+pub trait Visitor {
+    fn visit_sexpr(&mut self, sexpr: &Sexpr) {
+        walk_sexpr(self, sexpr)
+    }
+
+    fn visit_atom(&mut self, _atom: &Atom) {}
+}
+
+pub fn walk_sexpr<V: Visitor + ?Sized>(visitor: &mut V, sexpr: &Sexpr) {
+    match sexpr.kind() {
+        SexprKind::Atom(a) => visitor.visit_atom(a),
+        SexprKind::List(l) => {
+            l.iter().for_each(|s| visitor.visit_sexpr(s));
+            if let Some(tail) = l.dotted_tail() {
+                visitor.visit_sexpr(tail);
+            }
+        }
+        SexprKind::DataList(v) | SexprKind::Vector(v) | SexprKind::Set(v) => {
+            v.iter().for_each(|s| visitor.visit_sexpr(s))
+        }
+        SexprKind::Map(m) => {
+            for (k, v) in m {
+                visitor.visit_sexpr(k);
+                visitor.visit_sexpr(v);
+            }
+        }
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_sexpr_mut(&mut self, sexpr: &mut Sexpr) {
+        walk_sexpr_mut(self, sexpr)
+    }
+
+    fn visit_atom_mut(&mut self, _atom: &mut Atom) {}
+}
+
+pub fn walk_sexpr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, sexpr: &mut Sexpr) {
+    match &mut *sexpr.kind {
+        SexprKind::Atom(a) => visitor.visit_atom_mut(a),
+        SexprKind::List(l) => {
+            l.iter_mut().for_each(|s| visitor.visit_sexpr_mut(s));
+            if let Some(tail) = l.dotted_tail_mut() {
+                visitor.visit_sexpr_mut(tail);
+            }
+        }
+        SexprKind::DataList(v) | SexprKind::Vector(v) | SexprKind::Set(v) => {
+            v.iter_mut().for_each(|s| visitor.visit_sexpr_mut(s))
+        }
+        SexprKind::Map(m) => {
+            for (k, v) in m {
+                visitor.visit_sexpr_mut(k);
+                visitor.visit_sexpr_mut(v);
+            }
+        }
+    }
+}
+
+/// Rebuilds a tree bottom-up by value. Rebuilt sequences keep the span of
+/// the sequence they replace.
+pub trait Fold {
+    fn fold_sexpr(&mut self, sexpr: Sexpr) -> Sexpr {
+        fold_children(self, sexpr)
+    }
+
+    fn fold_atom(&mut self, atom: Atom) -> Atom {
+        atom
+    }
+}
+
+pub fn fold_children<F: Fold + ?Sized>(folder: &mut F, sexpr: Sexpr) -> Sexpr {
+    let span = sexpr.span;
+    let kind = match *sexpr.kind {
+        SexprKind::Atom(a) => SexprKind::Atom(folder.fold_atom(a)),
+        SexprKind::List(l) => {
+            let (items, tail) = l.into_parts();
+            let items = items.into_iter().map(|s| folder.fold_sexpr(s)).collect();
+            let tail = tail.map(|s| folder.fold_sexpr(s));
+            SexprKind::List(List::from_parts(items, tail))
+        }
+        SexprKind::DataList(v) => SexprKind::DataList(fold_seq(folder, v)),
+        SexprKind::Vector(v) => SexprKind::Vector(fold_seq(folder, v)),
+        SexprKind::Set(v) => SexprKind::Set(fold_seq(folder, v)),
+        SexprKind::Map(m) => SexprKind::Map(
+            m.into_iter()
+                .map(|(k, v)| (folder.fold_sexpr(k), folder.fold_sexpr(v)))
+                .collect(),
+        ),
+    };
+    Sexpr::new(kind, span)
+}
+
+fn fold_seq<F: Fold + ?Sized>(folder: &mut F, sexprs: Vec<Sexpr>) -> Vec<Sexpr> {
+    sexprs.into_iter().map(|s| folder.fold_sexpr(s)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold_children, walk_sexpr_mut, Fold, Visitor, VisitorMut};
+    use crate::read::{
+        read,
+        sexpr::{Atom, AtomKind, Sexpr},
+    };
+    use lust_utils::intern::InternedString;
+
+    #[test]
+    fn visit_in_source_order() {
+        struct Syms(Vec<String>);
+        impl Visitor for Syms {
+            fn visit_atom(&mut self, atom: &Atom) {
+                if let Some(s) = atom.as_sym() {
+                    self.0.push(s.to_string());
+                }
+            }
+        }
+        let (root, _) = read("(a [b #[c]] {d e} #{f} . g)");
+        let mut syms = Syms(vec![]);
+        syms.visit_sexpr(&root.sexprs[0]);
+        assert_eq!(syms.0, ["a", "b", "c", "d", "e", "f", "g"]);
+    }
+
+    #[test]
+    fn visit_mut_renames() {
+        struct Upper;
+        impl VisitorMut for Upper {
+            fn visit_atom_mut(&mut self, atom: &mut Atom) {
+                if let AtomKind::Sym(s) = &mut *atom.kind {
+                    *s = InternedString::from(s.to_uppercase());
+                }
+            }
+        }
+        let (mut root, _) = read("(f x . {k #[v]})");
+        Upper.visit_sexpr_mut(&mut root.sexprs[0]);
+        assert_eq!(root.sexprs[0].to_string(), "(F X . {K #[V]})");
+    }
+
+    #[test]
+    fn visit_mut_can_skip_subtrees() {
+        struct SkipQuote(usize);
+        impl VisitorMut for SkipQuote {
+            fn visit_sexpr_mut(&mut self, sexpr: &mut Sexpr) {
+                if sexpr.as_special_form() != Some("quote") {
+                    self.0 += 1;
+                    walk_sexpr_mut(self, sexpr)
+                }
+            }
+        }
+        let (mut root, _) = read("(a 'b (c 'd))");
+        let mut visitor = SkipQuote(0);
+        visitor.visit_sexpr_mut(&mut root.sexprs[0]);
+        assert_eq!(visitor.0, 4);
+    }
+
+    #[test]
+    fn fold_keeps_spans() {
+        // (f x) -> (f (g x))
+        struct Wrap;
+        impl Fold for Wrap {
+            fn fold_sexpr(&mut self, sexpr: Sexpr) -> Sexpr {
+                match sexpr.as_atom().and_then(|a| a.as_sym()) {
+                    Some(s) if &*s == "x" => {
+                        let (root, _) = read("(g x)");
+                        Sexpr::new(root.sexprs[0].kind().clone(), sexpr.span)
+                    }
+                    _ => fold_children(self, sexpr),
+                }
+            }
+        }
+        let (root, _) = read("(f x [x])");
+        let folded = Wrap.fold_sexpr(root.sexprs[0].clone());
+        assert_eq!(folded.to_string(), "(f (g x) [(g x)])");
+        assert_eq!(folded.span, root.sexprs[0].span);
+    }
+}
@@ -0,0 +1,302 @@
+/*
+ * A zipper over `Sexpr` trees: a focused sexpr plus the path of frames
+ * back to the root, so tools can walk to a form, edit it in place and
+ * rebuild the tree without hand-written recursion.
+ *
+ * Span bookkeeping: sequences rebuilt on the way up keep their original
+ * span, `replace_kind` keeps the span of the sexpr it replaces, and
+ * `spans` reports the spans of the enclosing forms for diagnostics.
+ */
+use super::sexpr::{Sexpr, SexprKind};
+use lust_utils::{list::List, span::Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeqKind {
+    List,
+    DataList,
+    Vector,
+    Map,
+    Set,
+}
+
+// A sequence with a hole where the focus goes. Map entries are flattened
+// into `k v` pairs of children, so edits to a map must keep them paired,
+// and an improper list's tail is its last child.
+#[derive(Debug, Clone)]
+struct Frame {
+    kind: SeqKind,
+    left: Vec<Sexpr>,
+    right: Vec<Sexpr>,
+    dotted: bool,
+    span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Zipper {
+    focus: Sexpr,
+    path: Vec<Frame>,
+}
+
+impl Zipper {
+    pub fn new(root: Sexpr) -> Self {
+        Self {
+            focus: root,
+            path: vec![],
+        }
+    }
+
+    pub fn focus(&self) -> &Sexpr {
+        &self.focus
+    }
+
+    pub fn focus_mut(&mut self) -> &mut Sexpr {
+        &mut self.focus
+    }
+
+    pub fn span(&self) -> Span {
+        self.focus.span
+    }
+
+    /// Spans of the forms enclosing the focus, innermost first.
+    pub fn spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.path.iter().rev().map(|frame| frame.span)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn is_top(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Moves to the first child of the focus.
+    pub fn down(mut self) -> Result<Self, Self> {
+        let span = self.focus.span;
+        let (kind, children, dotted) = match &*self.focus.kind {
+            SexprKind::Atom(_) => return Err(self),
+            SexprKind::List(l) => {
+                let (mut items, tail) = l.clone().into_parts();
+                let dotted = tail.is_some();
+                items.extend(tail);
+                (SeqKind::List, items, dotted)
+            }
+            SexprKind::DataList(v) => (SeqKind::DataList, v.clone(), false),
+            SexprKind::Vector(v) => (SeqKind::Vector, v.clone(), false),
+            SexprKind::Set(v) => (SeqKind::Set, v.clone(), false),
+            SexprKind::Map(m) => (
+                SeqKind::Map,
+                m.iter().flat_map(|(k, v)| [k.clone(), v.clone()]).collect(),
+                false,
+            ),
+        };
+        let mut children = children.into_iter();
+        let Some(first) = children.next() else {
+            return Err(self);
+        };
+        self.path.push(Frame {
+            kind,
+            left: vec![],
+            right: children.rev().collect(),
+            dotted,
+            span,
+        });
+        self.focus = first;
+        Ok(self)
+    }
+
+    /// Moves to the enclosing form, rebuilding it from the edited children.
+    pub fn up(mut self) -> Result<Self, Self> {
+        let Some(frame) = self.path.pop() else {
+            return Err(self);
+        };
+        let mut children = frame.left;
+        children.push(self.focus);
+        children.extend(frame.right.into_iter().rev());
+        self.focus = rebuild(frame.kind, children, frame.dotted, frame.span);
+        Ok(self)
+    }
+
+    pub fn left(mut self) -> Result<Self, Self> {
+        let Some(frame) = self.path.last_mut() else {
+            return Err(self);
+        };
+        let Some(prev) = frame.left.pop() else {
+            return Err(self);
+        };
+        frame.right.push(std::mem::replace(&mut self.focus, prev));
+        Ok(self)
+    }
+
+    pub fn right(mut self) -> Result<Self, Self> {
+        let Some(frame) = self.path.last_mut() else {
+            return Err(self);
+        };
+        let Some(next) = frame.right.pop() else {
+            return Err(self);
+        };
+        frame.left.push(std::mem::replace(&mut self.focus, next));
+        Ok(self)
+    }
+
+    /// Moves to the root, rebuilding every form on the way.
+    pub fn top(mut self) -> Self {
+        loop {
+            match self.up() {
+                Ok(z) => self = z,
+                Err(z) => return z,
+            }
+        }
+    }
+
+    pub fn into_sexpr(self) -> Sexpr {
+        self.top().focus
+    }
+
+    /// Replaces the focus, keeping the span of `sexpr`.
+    pub fn replace(&mut self, sexpr: Sexpr) -> Sexpr {
+        std::mem::replace(&mut self.focus, sexpr)
+    }
+
+    /// Replaces the focus, keeping the focus's span so the new sexpr still
+    /// points at the code it was derived from.
+    pub fn replace_kind(&mut self, kind: SexprKind) -> SexprKind {
+        std::mem::replace(&mut *self.focus.kind, kind)
+    }
+
+    pub fn edit(&mut self, f: impl FnOnce(Sexpr) -> Sexpr) {
+        let focus = std::mem::replace(&mut self.focus, placeholder());
+        self.focus = f(focus);
+    }
+
+    /// Inserts a sibling before the focus. Fails at the root.
+    pub fn insert_left(&mut self, sexpr: Sexpr) -> Result<(), Sexpr> {
+        match self.path.last_mut() {
+            Some(frame) => {
+                frame.left.push(sexpr);
+                Ok(())
+            }
+            None => Err(sexpr),
+        }
+    }
+
+    /// Inserts a sibling after the focus. Fails at the root and in front
+    /// of a dotted tail.
+    pub fn insert_right(&mut self, sexpr: Sexpr) -> Result<(), Sexpr> {
+        match self.path.last_mut() {
+            Some(frame) if !(frame.dotted && frame.right.is_empty()) => {
+                frame.right.push(sexpr);
+                Ok(())
+            }
+            _ => Err(sexpr),
+        }
+    }
+
+    /// Removes the focus and moves to its right sibling, else its left
+    /// sibling, else the now empty parent.
+    pub fn remove(mut self) -> Result<(Self, Sexpr), Self> {
+        let Some(frame) = self.path.last_mut() else {
+            return Err(self);
+        };
+        let removed = if let Some(next) = frame.right.pop() {
+            std::mem::replace(&mut self.focus, next)
+        } else if let Some(prev) = frame.left.pop() {
+            // the tail itself is gone, so the list becomes proper
+            frame.dotted = false;
+            std::mem::replace(&mut self.focus, prev)
+        } else {
+            let frame = self.path.pop().unwrap();
+            let parent = rebuild(frame.kind, vec![], false, frame.span);
+            std::mem::replace(&mut self.focus, parent)
+        };
+        Ok((self, removed))
+    }
+}
+
+fn rebuild(kind: SeqKind, mut children: Vec<Sexpr>, dotted: bool, span: Span) -> Sexpr {
+    let kind = match kind {
+        SeqKind::List => {
+            let tail = if dotted { children.pop() } else { None };
+            SexprKind::List(List::from_parts(children, tail))
+        }
+        SeqKind::DataList => SexprKind::DataList(children),
+        SeqKind::Vector => SexprKind::Vector(children),
+        SeqKind::Set => SexprKind::Set(children),
+        SeqKind::Map => {
+            let mut entries = vec![];
+            let mut iter = children.into_iter();
+            while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                entries.push((k, v));
+            }
+            SexprKind::Map(entries)
+        }
+    };
+    Sexpr::new(kind, span)
+}
+
+fn placeholder() -> Sexpr {
+    Sexpr::new(SexprKind::List(List::Empty), Span::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zipper;
+    use crate::read::{read, sexpr::SexprKind};
+
+    fn zip(src: &str) -> Zipper {
+        let (root, errs) = read(src);
+        assert!(errs.is_empty(), "{:?}", errs);
+        Zipper::new(root.sexprs[0].clone())
+    }
+
+    #[test]
+    fn navigate() {
+        let z = zip("(a (b c) . d)");
+        let z = z.down().unwrap().right().unwrap();
+        assert_eq!(z.focus().to_string(), "(b c)");
+        let z = z.down().unwrap().right().unwrap();
+        assert_eq!(z.focus().to_string(), "c");
+        assert_eq!(z.depth(), 2);
+        assert_eq!(z.spans().map(|s| s.start()).collect::<Vec<_>>(), [3, 0]);
+        let z = z.right().unwrap_err().up().unwrap().right().unwrap();
+        assert_eq!(z.focus().to_string(), "d");
+        let z = z.top();
+        assert!(z.is_top());
+        assert_eq!(z.focus().to_string(), "(a (b c) . d)");
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let z = zip("(f [x y] {:a 1})");
+        let mut z = z.down().unwrap().right().unwrap().down().unwrap();
+        let x_span = z.span();
+        let (root, _) = read("(inc 1)");
+        z = z.right().unwrap();
+        z.replace_kind(root.sexprs[0].kind().clone());
+        assert_eq!(z.span().start(), x_span.end() + 1);
+        z.insert_left(z.focus().clone()).unwrap();
+        let mut z = z.up().unwrap().right().unwrap().down().unwrap();
+        z.edit(|key| read(&format!("{}-key", key)).0.sexprs[0].clone());
+        let sexpr = z.into_sexpr();
+        assert_eq!(sexpr.to_string(), "(f [x (inc 1) (inc 1)] {:a-key 1})");
+        assert!(matches!(sexpr.kind(), SexprKind::List(_)));
+    }
+
+    #[test]
+    fn remove_and_insert() {
+        let z = zip("(a b . c)");
+        let z = z.down().unwrap().right().unwrap().right().unwrap();
+        let (mut z, removed) = z.remove().unwrap();
+        assert_eq!(removed.to_string(), "c");
+        z.insert_right(removed).unwrap();
+        assert_eq!(z.into_sexpr().to_string(), "(a b c)");
+
+        let mut z = zip("(a . b)").down().unwrap();
+        assert!(z.insert_right(z.focus().clone()).is_ok());
+        let mut z = zip("(a . b)").down().unwrap().right().unwrap();
+        assert!(z.insert_right(z.focus().clone()).is_err());
+
+        let (z, _) = zip("[x]").down().unwrap().remove().unwrap();
+        assert_eq!(z.focus().to_string(), "[]");
+    }
+}
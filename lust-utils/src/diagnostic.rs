@@ -0,0 +1,215 @@
+/*
+ * The error type every front-end pass reports through. Passes keep their
+ * own error types and convert them into `Diagnostic`s, which hosts like the
+ * REPL render against the source with ariadne.
+ */
+use crate::{
+    source::{FileId, SourceFile, SourceMap},
+    span::Span,
+};
+use ariadne::{Color, Config, Report, ReportKind, Source};
+use std::{
+    fmt::{Debug, Display},
+    io::{self, Write},
+    ops::Range,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Advice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub msg: Option<String>,
+    // primary labels point at the cause, secondary ones at related code
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub msg: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, msg: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            msg: msg.into(),
+            labels: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self::new(Severity::Error, msg)
+    }
+
+    pub fn warning(msg: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, msg)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Points at `span` without a label message of its own.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.labels.push(Label {
+            span,
+            msg: None,
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_primary(mut self, span: Span, msg: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            msg: Some(msg.into()),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, msg: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            msg: Some(msg.into()),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// The span of the first primary label, which is where the diagnostic
+    /// is reported.
+    pub fn span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .or(self.labels.first())
+            .map(|label| label.span)
+    }
+
+    /// Renders the diagnostic with snippets from `file`, which all label
+    /// spans are relative to.
+    pub fn write(
+        &self,
+        sources: &SourceMap,
+        file: FileId,
+        color: bool,
+        out: impl Write,
+    ) -> io::Result<()> {
+        let file = &sources[file];
+        let name = file.name().to_string();
+        let kind = match self.severity {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+            Severity::Advice => ReportKind::Advice,
+        };
+        let offset = self.span().map_or(0, |span| char_range(file, span).start);
+        let mut report = Report::build(kind, name.clone(), offset)
+            .with_config(Config::default().with_color(color))
+            .with_message(&self.msg);
+        if let Some(code) = self.code {
+            report = report.with_code(code);
+        }
+        for label in &self.labels {
+            let color = match (label.primary, self.severity) {
+                (false, _) => Color::Blue,
+                (true, Severity::Error) => Color::Red,
+                (true, Severity::Warning) => Color::Yellow,
+                (true, Severity::Advice) => Color::Cyan,
+            };
+            let mut l = ariadne::Label::new((name.clone(), char_range(file, label.span)))
+                .with_color(color)
+                .with_priority(label.primary as i32);
+            if let Some(msg) = &label.msg {
+                l = l.with_message(msg);
+            }
+            report = report.with_label(l);
+        }
+        for note in &self.notes {
+            report = report.with_note(note);
+        }
+        if let Some(help) = &self.help {
+            report = report.with_help(help);
+        }
+        report.finish().write((name, Source::from(file.src())), out)
+    }
+
+    /// Renders the diagnostic in color to stderr.
+    pub fn eprint(&self, sources: &SourceMap, file: FileId) -> io::Result<()> {
+        self.write(sources, file, true, io::stderr())
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error")?,
+            Severity::Warning => write!(f, "warning")?,
+            Severity::Advice => write!(f, "advice")?,
+        }
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.msg)?;
+        if let Some(span) = self.span() {
+            write!(f, " @ {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+// ariadne counts offsets in characters rather than bytes
+fn char_range(file: &SourceFile, span: Span) -> Range<usize> {
+    let start = file.snippet(Span::new(0, span.start())).chars().count();
+    start..start + file.snippet(span).chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+    use crate::{source::SourceMap, span::Span};
+
+    #[test]
+    fn render() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lt", "(def λ (f x\n");
+        let diag = Diagnostic::error("unclosed `(`")
+            .with_code("E0004")
+            .with_primary(Span::new(8, 9), "this `(` is never closed")
+            .with_secondary(Span::new(0, 1), "inside this form")
+            .with_help("add a `)`");
+        let mut out = vec![];
+        diag.write(&sources, file, false, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("[E0004] Error: unclosed `(`"), "{}", out);
+        assert!(out.contains("main.lt:1:8"), "{}", out);
+        assert!(out.contains("this `(` is never closed"), "{}", out);
+        assert!(out.contains("Help: add a `)`"), "{}", out);
+        assert_eq!(diag.to_string(), "error[E0004]: unclosed `(` @ 8..9");
+    }
+}
@@ -6,7 +6,7 @@ use std::{
     ops::Deref,
 };
 
-pub static mut INTERNER: Lazy<ThreadedRodeo> = Lazy::new(|| ThreadedRodeo::default());
+pub static INTERNER: Lazy<ThreadedRodeo> = Lazy::new(ThreadedRodeo::default);
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct InternedString {
@@ -22,7 +22,7 @@ impl From<Spur> for InternedString {
 impl From<&str> for InternedString {
     fn from(name: &str) -> Self {
         Self {
-            key: unsafe { INTERNER.get_or_intern(name) },
+            key: INTERNER.get_or_intern(name),
         }
     }
 }
@@ -30,28 +30,26 @@ impl From<&str> for InternedString {
 impl From<String> for InternedString {
     fn from(name: String) -> Self {
         Self {
-            key: unsafe { INTERNER.get_or_intern(name) },
+            key: INTERNER.get_or_intern(name),
         }
     }
 }
 
 impl Debug for InternedString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "InternedString({})", unsafe {
-            INTERNER.resolve(&self.key)
-        })
+        write!(f, "InternedString({})", INTERNER.resolve(&self.key))
     }
 }
 
 impl Display for InternedString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", unsafe { INTERNER.resolve(&self.key) })
+        write!(f, "{}", INTERNER.resolve(&self.key))
     }
 }
 
 impl Borrow<str> for InternedString {
     fn borrow(&self) -> &str {
-        unsafe { INTERNER.resolve(&self.key) }
+        INTERNER.resolve(&self.key)
     }
 }
 
@@ -59,6 +57,6 @@ impl Deref for InternedString {
     type Target = str;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { INTERNER.resolve(&self.key) }
+        INTERNER.resolve(&self.key)
     }
 }
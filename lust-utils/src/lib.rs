@@ -1,5 +1,7 @@
+pub mod diagnostic;
 pub mod intern;
 pub mod list;
 pub mod num;
+pub mod source;
 pub mod span;
 pub mod unique_id;
@@ -8,23 +8,86 @@ pub enum List<T> {
         head: T,
         tail: Box<Self>,
     },
+    // the `b` ending an improper list like `(a . b)`
+    Dotted(T),
 }
 
 impl<T> List<T> {
+    /// Builds `(items... . tail)`.
+    pub fn improper<I>(items: I, tail: T) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        <I as IntoIterator>::IntoIter: DoubleEndedIterator,
+    {
+        let mut list = Self::Dotted(tail);
+        for item in items.into_iter().rev() {
+            list.push_front(item);
+        }
+        list
+    }
+
+    /// Builds `(items...)`, or `(items... . tail)` when there is a tail.
+    pub fn from_parts(items: Vec<T>, tail: Option<T>) -> Self {
+        match tail {
+            Some(tail) => Self::improper(items, tail),
+            None => Self::from(items),
+        }
+    }
+
+    /// Splits the list into its proper elements and its dotted tail.
+    pub fn into_parts(self) -> (Vec<T>, Option<T>) {
+        let mut items = vec![];
+        let mut list = self;
+        loop {
+            match list {
+                Self::Empty => return (items, None),
+                Self::Dotted(tail) => return (items, Some(tail)),
+                Self::Pair { head, tail } => {
+                    items.push(head);
+                    list = *tail;
+                }
+            }
+        }
+    }
+
     pub fn head(&self) -> Option<&T> {
         match self {
-            Self::Empty => None,
+            Self::Empty | Self::Dotted(_) => None,
             Self::Pair { head, .. } => Some(head),
         }
     }
 
     pub fn tail(&self) -> Option<&Self> {
         match self {
-            Self::Empty => None,
+            Self::Empty | Self::Dotted(_) => None,
             Self::Pair { tail, .. } => Some(tail),
         }
     }
 
+    /// The element after the `.` of an improper list.
+    pub fn dotted_tail(&self) -> Option<&T> {
+        let mut list = self;
+        loop {
+            match list {
+                Self::Empty => return None,
+                Self::Dotted(tail) => return Some(tail),
+                Self::Pair { tail, .. } => list = tail,
+            }
+        }
+    }
+
+    pub fn dotted_tail_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Self::Empty => None,
+            Self::Dotted(tail) => Some(tail),
+            Self::Pair { tail, .. } => tail.dotted_tail_mut(),
+        }
+    }
+
+    pub fn is_proper(&self) -> bool {
+        self.dotted_tail().is_none()
+    }
+
     pub fn push_front(&mut self, head: T) {
         let tail = std::mem::replace(self, Self::Empty);
         *self = Self::Pair {
@@ -33,14 +96,17 @@ impl<T> List<T> {
         };
     }
 
+    /// Appends `head` to the proper part of the list, so `(a . b)` becomes
+    /// `(a head . b)`.
     pub fn push_back(&mut self, head: T) {
         let mut tail = self;
         loop {
             match tail {
-                Self::Empty => {
+                Self::Empty | Self::Dotted(_) => {
+                    let end = std::mem::replace(tail, Self::Empty);
                     *tail = Self::Pair {
                         head,
-                        tail: Box::new(Self::Empty),
+                        tail: Box::new(end),
                     };
                     break;
                 }
@@ -51,12 +117,31 @@ impl<T> List<T> {
         }
     }
 
+    /// Iterates over the proper elements, leaving out any dotted tail.
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         ListIter::new(self)
     }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        ListIterMut::new(self)
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> List<U> {
+        match self {
+            Self::Empty => List::Empty,
+            Self::Dotted(tail) => List::Dotted(f(tail)),
+            Self::Pair { head, tail } => {
+                let head = f(head);
+                List::Pair {
+                    head,
+                    tail: Box::new(tail.map(f)),
+                }
+            }
+        }
+    }
 }
 
-impl<'a, T> Display for List<T>
+impl<T> Display for List<T>
 where
     T: Display,
 {
@@ -68,6 +153,9 @@ where
             }
             write!(f, "{}", s)?;
         }
+        if let Some(tail) = self.dotted_tail() {
+            write!(f, " . {}", tail)?;
+        }
         write!(f, ")")
     }
 }
@@ -102,7 +190,7 @@ impl<'a, T> Iterator for ListIter<'a, T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.list {
-            List::Empty => None,
+            List::Empty | List::Dotted(_) => None,
             List::Pair { head, tail } => {
                 self.list = tail;
                 Some(head)
@@ -111,27 +199,58 @@ impl<'a, T> Iterator for ListIter<'a, T> {
     }
 }
 
-// #[derive(Debug)]
-// pub struct ListIterMut<'a, T> {
-//     list: &'a mut List<T>,
-// }
-
-// impl<'a, T> ListIterMut<'a, T> {
-//     pub fn new(list: &'a mut List<T>) -> Self {
-//         Self { list }
-//     }
-// }
-
-// impl<'a, T> Iterator for ListIterMut<'a, T> {
-//     type Item = &'a mut T;
-
-//     fn next(&mut self) -> Option<Self::Item> {
-//         match self.list {
-//             List::Empty => None,
-//             List::Pair { head, tail } => {
-//                 self.list = tail;
-//                 Some(head)
-//             }
-//         }
-//     }
-// }
+#[derive(Debug)]
+pub struct ListIterMut<'a, T> {
+    list: Option<&'a mut List<T>>,
+}
+
+impl<'a, T> ListIterMut<'a, T> {
+    pub fn new(list: &'a mut List<T>) -> Self {
+        Self { list: Some(list) }
+    }
+}
+
+impl<'a, T> Iterator for ListIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.list.take()? {
+            List::Empty | List::Dotted(_) => None,
+            List::Pair { head, tail } => {
+                self.list = Some(tail);
+                Some(head)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::List;
+
+    #[test]
+    fn improper_list() {
+        let mut list = List::improper([1, 2], 3);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(list.dotted_tail(), Some(&3));
+        assert!(!list.is_proper());
+        list.push_back(4);
+        assert_eq!(list.to_string(), "(1 2 4 . 3)");
+        assert_eq!(list.map(|i| i * 10).to_string(), "(10 20 40 . 30)");
+        assert!(List::from([1, 2]).is_proper());
+    }
+
+    #[test]
+    fn list_parts() {
+        let mut list = List::improper([1, 2], 3);
+        list.iter_mut().for_each(|i| *i += 1);
+        *list.dotted_tail_mut().unwrap() += 1;
+        assert_eq!(list.clone().into_parts(), (vec![2, 3], Some(4)));
+        let (items, tail) = list.clone().into_parts();
+        assert_eq!(List::from_parts(items, tail), list);
+        assert_eq!(
+            List::from_parts(vec![1], None).into_parts(),
+            (vec![1], None)
+        );
+    }
+}
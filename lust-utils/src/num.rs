@@ -1,10 +1,49 @@
 use num_bigint::BigInt as NumBigInt;
 use num_rational::{BigRational as NumBigRational, Rational64};
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, num::IntErrorKind, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseNumError {
+    Invalid,
+    Overflow,
+    ZeroDenominator,
+}
+
+impl Display for ParseNumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseNumError::Invalid => write!(f, "invalid number literal"),
+            ParseNumError::Overflow => write!(f, "number literal out of range"),
+            ParseNumError::ZeroDenominator => write!(f, "rational with zero denominator"),
+        }
+    }
+}
+
+// Splits an integer literal into its signed digits and radix, dropping the
+// `0b`/`0o`/`0x` prefix and any `_` separators.
+fn split_radix(s: &str) -> (String, u32) {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = match s.get(..2) {
+        Some("0b") => (2, &s[2..]),
+        Some("0o") => (8, &s[2..]),
+        Some("0x") => (16, &s[2..]),
+        _ => (10, s),
+    };
+    (format!("{}{}", sign, digits.replace('_', "")), radix)
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Int(i64);
 
+impl Int {
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+}
+
 impl Display for Int {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -12,10 +51,19 @@ impl Display for Int {
 }
 
 impl FromStr for Int {
-    type Err = std::num::ParseIntError;
+    type Err = ParseNumError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(s.parse()?))
+        let (digits, radix) = split_radix(s);
+        match i64::from_str_radix(&digits, radix) {
+            Ok(n) => Ok(Self(n)),
+            Err(e) => match e.kind() {
+                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                    Err(ParseNumError::Overflow)
+                }
+                _ => Err(ParseNumError::Invalid),
+            },
+        }
     }
 }
 
@@ -29,10 +77,13 @@ impl Display for BigInt {
 }
 
 impl FromStr for BigInt {
-    type Err = num_bigint::ParseBigIntError;
+    type Err = ParseNumError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(s.parse()?))
+        let (digits, radix) = split_radix(s);
+        NumBigInt::parse_bytes(digits.as_bytes(), radix)
+            .map(Self)
+            .ok_or(ParseNumError::Invalid)
     }
 }
 
@@ -41,15 +92,31 @@ pub struct Real(f64);
 
 impl Display for Real {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        if self.0.is_nan() {
+            write!(f, "+nan.0")
+        } else if self.0.is_infinite() {
+            write!(f, "{}inf.0", if self.0 > 0.0 { "+" } else { "-" })
+        } else {
+            // `Debug` keeps the trailing `.0` so the output reads back as a real
+            write!(f, "{:?}", self.0)
+        }
     }
 }
 
 impl FromStr for Real {
-    type Err = std::num::ParseFloatError;
+    type Err = ParseNumError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(s.parse()?))
+        match s {
+            "+inf.0" => Ok(Self(f64::INFINITY)),
+            "-inf.0" => Ok(Self(f64::NEG_INFINITY)),
+            "+nan.0" | "-nan.0" => Ok(Self(f64::NAN)),
+            _ => s
+                .replace('_', "")
+                .parse()
+                .map(Self)
+                .map_err(|_| ParseNumError::Invalid),
+        }
     }
 }
 
@@ -81,10 +148,31 @@ impl Display for Rational {
 }
 
 impl FromStr for Rational {
-    type Err = num_rational::ParseRatioError;
+    type Err = ParseNumError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(s.parse()?))
+        let (numer, denom) = s.split_once('/').unwrap_or((s, "1"));
+        let (numer, denom) = (numer.parse::<Int>()?, denom.parse::<Int>()?);
+        if denom.0 == 0 {
+            return Err(ParseNumError::ZeroDenominator);
+        }
+        // Reduce on the magnitudes so `i64::MIN` never has to be negated;
+        // a result that still doesn't fit is promoted by `Fraction`.
+        let (mut n, mut d) = (numer.0.unsigned_abs(), denom.0.unsigned_abs());
+        let (mut a, mut b) = (n, d);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        (n, d) = (n / a, d / a);
+        let n = if (numer.0 < 0) != (denom.0 < 0) {
+            -(n as i128)
+        } else {
+            n as i128
+        };
+        match (i64::try_from(n), i64::try_from(d)) {
+            (Ok(n), Ok(d)) => Ok(Self(Rational64::new_raw(n, d))),
+            _ => Err(ParseNumError::Overflow),
+        }
     }
 }
 
@@ -98,9 +186,138 @@ impl Display for BigRational {
 }
 
 impl FromStr for BigRational {
-    type Err = num_rational::ParseRatioError;
+    type Err = ParseNumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (numer, denom) = s.split_once('/').unwrap_or((s, "1"));
+        let (numer, denom) = (numer.parse::<BigInt>()?, denom.parse::<BigInt>()?);
+        if denom.0 == NumBigInt::from(0) {
+            return Err(ParseNumError::ZeroDenominator);
+        }
+        Ok(Self(NumBigRational::new(numer.0, denom.0)))
+    }
+}
+
+/// An integer literal, promoted to `BigInt` when it doesn't fit in an `i64`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Integer {
+    Small(Int),
+    Big(BigInt),
+}
+
+impl Display for Integer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Integer::Small(i) => write!(f, "{}", i),
+            Integer::Big(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+impl FromStr for Integer {
+    type Err = ParseNumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(i) => Ok(Self::Small(i)),
+            Err(ParseNumError::Overflow) => Ok(Self::Big(s.parse()?)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A rational literal, promoted to `BigRational` when its numerator or
+/// denominator doesn't fit in an `i64`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fraction {
+    Small(Rational),
+    Big(BigRational),
+}
+
+impl Display for Fraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fraction::Small(r) => write!(f, "{}", r),
+            Fraction::Big(r) => write!(f, "{}", r),
+        }
+    }
+}
+
+impl FromStr for Fraction {
+    type Err = ParseNumError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self(s.parse()?))
+        match s.parse() {
+            Ok(r) => Ok(Self::Small(r)),
+            Err(ParseNumError::Overflow) => Ok(Self::Big(s.parse()?)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fraction, Int, Integer, ParseNumError, Rational, Real};
+
+    #[test]
+    fn parse_int_radix() {
+        assert_eq!("0xff".parse::<Int>(), Ok(Int(255)));
+        assert_eq!("-0b101".parse::<Int>(), Ok(Int(-5)));
+        assert_eq!("+0o17".parse::<Int>(), Ok(Int(15)));
+        assert_eq!("1_000_000".parse::<Int>(), Ok(Int(1_000_000)));
+        assert_eq!("0x".parse::<Int>(), Err(ParseNumError::Invalid));
+    }
+
+    #[test]
+    fn parse_integer_promotes() {
+        assert!(matches!(
+            "9223372036854775807".parse(),
+            Ok(Integer::Small(_))
+        ));
+        assert!(matches!("9223372036854775808".parse(), Ok(Integer::Big(_))));
+        assert!(matches!(
+            "-0xffffffffffffffffff".parse(),
+            Ok(Integer::Big(_))
+        ));
+    }
+
+    #[test]
+    fn parse_rational() {
+        assert_eq!("-2/4".parse::<Rational>(), Ok(Rational::new(-1, 2)));
+        assert_eq!("0x10/0b10".parse::<Rational>(), Ok(Rational::new(8, 1)));
+        assert_eq!(
+            "1/0".parse::<Rational>(),
+            Err(ParseNumError::ZeroDenominator)
+        );
+        assert!(matches!(
+            "1/99999999999999999999".parse(),
+            Ok(Fraction::Big(_))
+        ));
+        assert_eq!(
+            "-9223372036854775808/-1".parse::<Rational>(),
+            Err(ParseNumError::Overflow)
+        );
+        assert!(matches!(
+            "-9223372036854775808/-1".parse(),
+            Ok(Fraction::Big(_))
+        ));
+        assert_eq!(
+            "-9223372036854775808/-2".parse::<Rational>(),
+            Ok(Rational::new(1 << 62, 1))
+        );
+        assert_eq!(
+            "-9223372036854775808/-9223372036854775808".parse::<Rational>(),
+            Ok(Rational::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn parse_real() {
+        assert_eq!("1e3".parse::<Real>(), Ok(Real(1000.0)));
+        assert_eq!("-1_000.5".parse::<Real>(), Ok(Real(-1000.5)));
+        assert_eq!("-inf.0".parse::<Real>(), Ok(Real(f64::NEG_INFINITY)));
+        assert!("+nan.0".parse::<Real>().unwrap().0.is_nan());
+        assert_eq!(Real(1.0).to_string(), "1.0");
+        assert_eq!(Real(f64::INFINITY).to_string(), "+inf.0");
     }
 }
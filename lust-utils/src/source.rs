@@ -0,0 +1,271 @@
+use crate::span::Span;
+use std::{
+    fmt::{Debug, Display},
+    ops::Index,
+};
+
+/// Identifies a file added to a `SourceMap`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(u32);
+
+impl Debug for FileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FileId({})", self.0)
+    }
+}
+
+/// A span together with the file its offsets are relative to.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileSpan {
+    pub file: FileId,
+    pub span: Span,
+}
+
+impl FileSpan {
+    pub fn new(file: FileId, span: Span) -> Self {
+        Self { file, span }
+    }
+}
+
+impl Debug for FileSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{:?}", self.file.0, self.span)
+    }
+}
+
+/// A zero-based line and column. Whether `col` counts bytes or UTF-16 code
+/// units depends on the method that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl Display for LineCol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.col + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    name: String,
+    src: String,
+    // byte offset of the start of every line
+    line_starts: Vec<u32>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, src: impl Into<String>) -> Self {
+        let src = src.into();
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i as u32 + 1))
+            .collect();
+        Self {
+            name: name.into(),
+            src,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The line containing `offset`. Offsets past the end of the file
+    /// belong to the last line.
+    pub fn line_index(&self, offset: u32) -> u32 {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line as u32,
+            Err(next) => next as u32 - 1,
+        }
+    }
+
+    /// The span of `line`, not including its line break.
+    pub fn line_span(&self, line: u32) -> Option<Span> {
+        let start = *self.line_starts.get(line as usize)?;
+        let end = match self.line_starts.get(line as usize + 1) {
+            Some(next) => next - 1,
+            None => self.src.len() as u32,
+        };
+        let end = if self.src[..end as usize].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+        Some(Span::new(start, end))
+    }
+
+    /// The line and byte column of `offset`.
+    pub fn line_col(&self, offset: u32) -> LineCol {
+        let offset = self.clamp(offset);
+        let line = self.line_index(offset);
+        LineCol {
+            line,
+            col: offset - self.line_starts[line as usize],
+        }
+    }
+
+    /// The line and UTF-16 column of `offset`, as editors using the
+    /// Language Server Protocol expect.
+    pub fn line_col_utf16(&self, offset: u32) -> LineCol {
+        let offset = self.clamp(offset);
+        let line = self.line_index(offset);
+        let start = self.line_starts[line as usize] as usize;
+        let col = self.src[start..offset as usize]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        LineCol {
+            line,
+            col: col as u32,
+        }
+    }
+
+    /// The byte offset of a line and byte column, if it's inside the file.
+    pub fn offset(&self, pos: LineCol) -> Option<u32> {
+        let line = self.line_span(pos.line)?;
+        let offset = line.start().checked_add(pos.col)?;
+        (offset <= line.end() && self.src.is_char_boundary(offset as usize)).then_some(offset)
+    }
+
+    /// The byte offset of a line and UTF-16 column, if it's inside the file.
+    pub fn offset_utf16(&self, pos: LineCol) -> Option<u32> {
+        let line = self.line_span(pos.line)?;
+        let mut col = 0;
+        for (i, c) in self.src[line].char_indices() {
+            if col == pos.col as usize {
+                return line.start().checked_add(i as u32);
+            }
+            col += c.len_utf16();
+        }
+        (col == pos.col as usize).then_some(line.end())
+    }
+
+    /// The source text covered by `span`.
+    pub fn snippet(&self, span: Span) -> &str {
+        &self.src[Span::new(self.clamp(span.start()), self.clamp(span.end()))]
+    }
+
+    fn clamp(&self, offset: u32) -> u32 {
+        let mut offset = offset.min(self.src.len() as u32);
+        while !self.src.is_char_boundary(offset as usize) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+/// Owns the source of every file in a program so spans from any of them
+/// can be resolved to file names, lines and columns.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.files.push(SourceFile::new(name, src));
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i as u32), file))
+    }
+
+    pub fn line_col(&self, span: FileSpan) -> LineCol {
+        self[span.file].line_col(span.span.start())
+    }
+
+    pub fn snippet(&self, span: FileSpan) -> &str {
+        self[span.file].snippet(span.span)
+    }
+}
+
+impl Index<FileId> for SourceMap {
+    type Output = SourceFile;
+
+    fn index(&self, file: FileId) -> &Self::Output {
+        &self.files[file.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileSpan, LineCol, SourceFile, SourceMap};
+    use crate::span::Span;
+
+    #[test]
+    fn line_col() {
+        let file = SourceFile::new("a.lt", "(def x 1)\r\n(f x)\n");
+        assert_eq!(file.line_count(), 3);
+        assert_eq!(file.line_col(0), LineCol { line: 0, col: 0 });
+        assert_eq!(file.line_col(12), LineCol { line: 1, col: 1 });
+        assert_eq!(file.line_col(17), LineCol { line: 2, col: 0 });
+        assert_eq!(file.line_col(99), LineCol { line: 2, col: 0 });
+        assert_eq!(file.line_span(0), Some(Span::new(0, 9)));
+        assert_eq!(file.line_span(1), Some(Span::new(11, 16)));
+        assert_eq!(file.offset(LineCol { line: 1, col: 4 }), Some(15));
+        assert_eq!(file.offset(LineCol { line: 1, col: 9 }), None);
+        assert_eq!(
+            file.offset(LineCol {
+                line: 1,
+                col: u32::MAX
+            }),
+            None
+        );
+        assert_eq!(file.line_col(12).to_string(), "2:2");
+    }
+
+    #[test]
+    fn utf16_columns() {
+        let file = SourceFile::new("a.lt", "\"λ😀\" x");
+        // λ is one UTF-16 unit but two bytes, 😀 is two units but four bytes
+        assert_eq!(file.line_col(8), LineCol { line: 0, col: 8 });
+        assert_eq!(file.line_col_utf16(8), LineCol { line: 0, col: 5 });
+        assert_eq!(file.line_col_utf16(9), LineCol { line: 0, col: 6 });
+        assert_eq!(file.offset_utf16(LineCol { line: 0, col: 6 }), Some(9));
+        assert_eq!(file.offset_utf16(LineCol { line: 0, col: 3 }), None);
+        assert_eq!(
+            file.offset_utf16(LineCol {
+                line: 0,
+                col: u32::MAX
+            }),
+            None
+        );
+        // offsets inside a character resolve to its start
+        assert_eq!(file.line_col_utf16(4), LineCol { line: 0, col: 2 });
+    }
+
+    #[test]
+    fn source_map() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.lt", "(def x 1)");
+        let b = map.add("b.lt", "\n(f x)");
+        assert_ne!(a, b);
+        assert_eq!(map[b].name(), "b.lt");
+        let span = FileSpan::new(b, Span::new(1, 6));
+        assert_eq!(map.snippet(span), "(f x)");
+        assert_eq!(map.line_col(span), LineCol { line: 1, col: 0 });
+        assert_eq!(map.files().count(), 2);
+    }
+}
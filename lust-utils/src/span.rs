@@ -71,16 +71,14 @@ impl chumsky::span::Span for Span {
 
     type Offset = u32;
 
-    fn new(context: Self::Context, range: Range<Self::Offset>) -> Self {
+    fn new(_context: Self::Context, range: Range<Self::Offset>) -> Self {
         Self {
             start: range.start,
             end: range.end,
         }
     }
 
-    fn context(&self) -> Self::Context {
-        ()
-    }
+    fn context(&self) -> Self::Context {}
 
     fn start(&self) -> Self::Offset {
         self.start
@@ -1,6 +1,25 @@
-use lust_repl::repl;
+use clap::Parser;
+use lust_repl::{check_file, repl};
+use std::{path::PathBuf, process::exit};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// File to check; starts the REPL when omitted
+    file: Option<PathBuf>,
+}
 
 fn main() {
     env_logger::init();
-    repl()
+    match Cli::parse().file {
+        Some(path) => match check_file(&path) {
+            Ok(true) => (),
+            Ok(false) => exit(1),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                exit(1)
+            }
+        },
+        None => repl(),
+    }
 }